@@ -0,0 +1,76 @@
+//! Finding the bricks a ball hits in one step, with the physics sweep run
+//! against every brick versus only the ones the grid puts near its path.
+//!
+//! Run with `cargo bench --bench broadphase`.
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use macroquad::prelude::*;
+use rayball::bench::{sweep_bricks, swept_area};
+use rayball::components::{Ball, Brick, BrickKind};
+use rayball::constants::ARENA;
+use rayball::rng::Rng;
+use rayball::systems::broadphase::BrickGrid;
+
+/// A wall of `cols` x `rows` bricks filling the top of the arena.
+fn brick_field(cols: usize, rows: usize) -> Vec<Brick> {
+    let width = ARENA.x / cols as f32;
+    let height = 300. / rows as f32;
+    let mut bricks = Vec::with_capacity(cols * rows);
+    for row in 0..rows {
+        for col in 0..cols {
+            let pos = vec2(col as f32 * width, 50. + row as f32 * height);
+            let mut brick = Brick::new(pos, BrickKind::Normal, 1, WHITE);
+            brick.width = width - 1.;
+            brick.height = height - 1.;
+            bricks.push(brick);
+        }
+    }
+    bricks
+}
+
+/// Balls scattered over the arena, each moving as far as a fast ball does
+/// in one tick.
+fn balls(count: usize) -> Vec<(Ball, Vec2)> {
+    let mut rng = Rng::new(22);
+    (0..count)
+        .map(|_| {
+            let mut ball = Ball::new();
+            ball.pos = vec2(rng.range_f32(0., ARENA.x), rng.range_f32(0., ARENA.y));
+            let motion = vec2(rng.range_f32(-1., 1.), rng.range_f32(-1., 1.)).normalize() * 6.;
+            (ball, motion)
+        })
+        .collect()
+}
+
+fn broadphase(c: &mut Criterion) {
+    let balls = balls(64);
+    let mut group = c.benchmark_group("brick hits, 64 balls");
+
+    for (cols, rows) in [(10, 4), (40, 25), (100, 50)] {
+        let bricks = brick_field(cols, rows);
+        let mut grid = BrickGrid::new(ARENA);
+        grid.rebuild(&bricks);
+        let count = bricks.len();
+
+        group.bench_with_input(BenchmarkId::new("linear", count), &bricks, |b, bricks| {
+            b.iter(|| {
+                for (ball, motion) in &balls {
+                    black_box(sweep_bricks(ball, *motion, bricks, 0..bricks.len()));
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("grid", count), &bricks, |b, bricks| {
+            let mut nearby = Vec::new();
+            b.iter(|| {
+                for (ball, motion) in &balls {
+                    grid.query(swept_area(ball, *motion), &mut nearby);
+                    black_box(sweep_bricks(ball, *motion, bricks, nearby.iter().copied()));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, broadphase);
+criterion_main!(benches);
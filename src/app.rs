@@ -0,0 +1,115 @@
+use crate::game::{Game, Setup};
+use crate::scenes::{Context, Playing, Scene, TitleScreen, Transition};
+use macroquad::prelude::*;
+use rayball::input::Bindings;
+use rayball::levels::draft::Draft;
+use rayball::replay::Replay;
+use rayball::settings::Settings;
+use rayball::systems::audio::SoundManager;
+use rayball::systems::highscore::HighScores;
+
+/// The scene stack and the state its scenes share.
+pub struct App {
+    ctx: Context,
+    scenes: Vec<Scene>,
+    /// Whether the mouse is hidden and held by the window.
+    cursor_grabbed: bool,
+}
+
+impl App {
+    pub fn new(sounds: SoundManager, settings: Settings, setup: Setup) -> Self {
+        let mut app = App {
+            ctx: Context {
+                game: None,
+                sounds,
+                high_scores: HighScores::load(),
+                bindings: Bindings::load(),
+                settings,
+                setup,
+                draft: Draft::default(),
+                muted: false,
+            },
+            scenes: vec![Scene::Title(TitleScreen::default())],
+            cursor_grabbed: false,
+        };
+        app.ctx.apply_settings();
+        app
+    }
+
+    /// Goes straight into watching `replay`.
+    pub fn watch(&mut self, replay: Replay) {
+        self.ctx.start_game(Game::replay(replay));
+        self.scenes = vec![Scene::Playing(Playing)];
+    }
+
+    /// Goes straight into a run, skipping the title screen.
+    pub fn play(&mut self) {
+        self.ctx.start_game(Game::new(&self.ctx.setup));
+        self.scenes = vec![Scene::Playing(Playing)];
+    }
+
+    /// Silences the game for this session, whatever the settings say.
+    pub fn mute(&mut self) {
+        self.ctx.muted = true;
+        self.ctx.apply_settings();
+    }
+
+    /// Updates the top scene. Returns false once the player quits.
+    pub fn update(&mut self) -> bool {
+        let Some(scene) = self.scenes.last_mut() else {
+            return false;
+        };
+        match scene.update(&mut self.ctx) {
+            Transition::None => {}
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scene) => self.scenes = vec![scene],
+            Transition::Quit => return false,
+        }
+        self.grab_cursor(self.wants_cursor_grabbed());
+        !self.scenes.is_empty()
+    }
+
+    /// The mouse steers the paddle only while playing live, and is free in
+    /// every menu.
+    fn wants_cursor_grabbed(&self) -> bool {
+        let playing = matches!(self.scenes.last(), Some(Scene::Playing(_)));
+        let live = self.ctx.game.as_ref().is_some_and(|g| !g.is_replay());
+        playing && live && self.ctx.settings.controls.mouse.enabled
+    }
+
+    fn grab_cursor(&mut self, grab: bool) {
+        if grab != self.cursor_grabbed {
+            set_cursor_grab(grab);
+            show_mouse(!grab);
+            self.cursor_grabbed = grab;
+        }
+    }
+
+    /// Draws the run in progress, if any, with the top scene over it.
+    pub fn draw(&self) {
+        if let Some(game) = &self.ctx.game {
+            game.draw(&self.ctx.bindings);
+        }
+        if let Some(scene) = self.scenes.last() {
+            scene.draw(&self.ctx);
+        }
+    }
+
+    /// Frames per second to hold the main loop to, 0 for as fast as it goes.
+    pub fn fps_cap(&self) -> u32 {
+        self.ctx.settings.display.fps_cap
+    }
+
+    /// Saves what needs saving before the window closes.
+    pub fn shutdown(&mut self) {
+        self.grab_cursor(false);
+        self.ctx.end_game();
+    }
+}
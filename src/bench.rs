@@ -0,0 +1,19 @@
+//! Internals the benchmarks measure. Not part of the API.
+
+use crate::components::{Ball, Brick};
+use crate::systems::collision::Hit;
+use crate::systems::physics;
+use macroquad::prelude::*;
+
+pub fn sweep_bricks(
+    ball: &Ball,
+    motion: Vec2,
+    bricks: &[Brick],
+    candidates: impl IntoIterator<Item = usize>,
+) -> Vec<(usize, Hit)> {
+    physics::sweep_bricks(ball, motion, bricks, candidates)
+}
+
+pub fn swept_area(ball: &Ball, motion: Vec2) -> Rect {
+    physics::swept_area(ball, motion)
+}
@@ -0,0 +1,120 @@
+//! Plays replays headlessly and checks each one ends the way it recorded.
+//!
+//! Usage: `rayball-verify [--bless] <replay or directory>...`
+//!
+//! Directories are searched for `.replay` files. With `--bless`, the
+//! expectations in each file are rewritten to match the current simulation
+//! instead of checked, for when gameplay changed on purpose.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rayball::replay::{Outcome, Replay};
+
+fn main() -> ExitCode {
+    let mut bless = false;
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--bless" => bless = true,
+            "-h" | "--help" => {
+                println!("usage: rayball-verify [--bless] <replay or directory>...");
+                return ExitCode::SUCCESS;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("usage: rayball-verify [--bless] <replay or directory>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(e) = collect_replays(path, &mut files) {
+            eprintln!("{}: {e}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        let ok = if bless {
+            bless_one(file)
+        } else {
+            verify_one(file)
+        };
+        if !ok {
+            failed += 1;
+        }
+    }
+
+    println!("{} replays, {} failed", files.len(), failed);
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn collect_replays(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+    let mut found: Vec<PathBuf> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "replay"))
+        .collect();
+    found.sort();
+    files.extend(found);
+    Ok(())
+}
+
+fn verify_one(file: &Path) -> bool {
+    let replay = match Replay::load(file) {
+        Ok(replay) => replay,
+        Err(e) => {
+            println!("FAIL {}: {e}", file.display());
+            return false;
+        }
+    };
+    match replay.verify() {
+        Ok(mismatches) if mismatches.is_empty() => {
+            println!("ok   {} ({} ticks)", file.display(), replay.inputs.len());
+            true
+        }
+        Ok(mismatches) => {
+            println!("FAIL {}", file.display());
+            for mismatch in mismatches {
+                println!("     {mismatch}");
+            }
+            false
+        }
+        Err(e) => {
+            println!("FAIL {}: {e}", file.display());
+            false
+        }
+    }
+}
+
+fn bless_one(file: &Path) -> bool {
+    let mut replay = match Replay::load(file) {
+        Ok(replay) => replay,
+        Err(e) => {
+            println!("FAIL {}: {e}", file.display());
+            return false;
+        }
+    };
+    replay.expected = Some(Outcome::of(&replay.run()));
+    match replay.save(file) {
+        Ok(()) => {
+            println!("bless {}", file.display());
+            true
+        }
+        Err(e) => {
+            println!("FAIL {}: {e}", file.display());
+            false
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Command-line options of the game binary.
+
+use crate::game::Setup;
+use rayball::levels::generator::{self, Difficulty};
+use rayball::levels::{CAMPAIGN, Course, Level};
+use rayball::replay::Replay;
+use rayball::settings::{Display, FPS_CAP_RANGE, HEIGHT_RANGE, WIDTH_RANGE};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: rayball [options]
+
+  --level <n>          start the campaign on stage n
+  --level-file <file>  play a single level from a .lvl file
+  --generate <n>       play a level generated from seed n
+  --difficulty <name>  easy, normal or hard, for --generate
+  --endless            play endless mode
+  --seed <n>           seed the simulation instead of using the clock
+  --replay <file>      watch a recorded session
+  --width <pixels>     window width
+  --height <pixels>    window height
+  --fullscreen         start fullscreen
+  --windowed           start in a window
+  --fps <n>            frame rate limit, 0 for none
+  --mute               no sound this session
+  --log-level <level>  off, error, warn, info, debug or trace
+  --log-file <file>    where to write the log (default rayball.log)
+  --headless           run without a window and print a summary
+  --ticks <n>          ticks to run headless, at 240 per second
+  -h, --help           show this help
+
+Window, frame rate and sound options apply to this session only and
+don't change the saved settings.";
+
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Campaign stage to start on, counted from 1.
+    pub level: Option<usize>,
+    pub level_file: Option<PathBuf>,
+    /// Seed of a generated level to play.
+    pub generate: Option<u64>,
+    pub difficulty: Option<Difficulty>,
+    pub endless: bool,
+    pub seed: Option<u64>,
+    pub replay: Option<PathBuf>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: Option<bool>,
+    pub fps_cap: Option<u32>,
+    pub mute: bool,
+    pub log_level: Option<log::LevelFilter>,
+    pub log_file: Option<PathBuf>,
+    pub headless: bool,
+    pub ticks: Option<u64>,
+    pub help: bool,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Both `--flag value` and `--flag=value` work
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_owned(), Some(value.to_owned()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("{flag} needs a value"))
+            };
+
+            match flag.as_str() {
+                "--level" => {
+                    let level = parse(&flag, &value()?)?;
+                    if !(1..=CAMPAIGN.len()).contains(&level) {
+                        return Err(format!("--level must be between 1 and {}", CAMPAIGN.len()));
+                    }
+                    options.level = Some(level);
+                }
+                "--level-file" => options.level_file = Some(value()?.into()),
+                "--generate" => options.generate = Some(parse(&flag, &value()?)?),
+                "--difficulty" => {
+                    let name = value()?;
+                    options.difficulty = Some(
+                        Difficulty::named(&name).ok_or(format!("unknown difficulty `{name}`"))?,
+                    );
+                }
+                "--endless" => options.endless = true,
+                "--seed" => options.seed = Some(parse(&flag, &value()?)?),
+                "--replay" => options.replay = Some(value()?.into()),
+                "--width" => options.width = Some(in_range(&flag, &value()?, WIDTH_RANGE)?),
+                "--height" => options.height = Some(in_range(&flag, &value()?, HEIGHT_RANGE)?),
+                "--fullscreen" => options.fullscreen = Some(true),
+                "--windowed" => options.fullscreen = Some(false),
+                "--fps" => {
+                    let fps = value()?;
+                    options.fps_cap = Some(match fps.as_str() {
+                        "0" => 0,
+                        _ => in_range(&flag, &fps, FPS_CAP_RANGE)?,
+                    });
+                }
+                "--mute" => options.mute = true,
+                "--log-level" => {
+                    let level = value()?;
+                    options.log_level = Some(
+                        level
+                            .parse()
+                            .map_err(|_| format!("unknown log level `{level}`"))?,
+                    );
+                }
+                "--log-file" => options.log_file = Some(value()?.into()),
+                "--headless" => options.headless = true,
+                "--ticks" => options.ticks = Some(parse(&flag, &value()?)?),
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("unknown option `{arg}`")),
+            }
+            if inline.is_some() && !flag_takes_value(&flag) {
+                return Err(format!("{flag} doesn't take a value"));
+            }
+        }
+        options.check()?;
+        Ok(options)
+    }
+
+    /// Catches combinations that make no sense together.
+    fn check(&self) -> Result<(), String> {
+        let starts = [
+            self.level.is_some(),
+            self.level_file.is_some(),
+            self.generate.is_some(),
+            self.endless,
+            self.replay.is_some(),
+        ];
+        if starts.iter().filter(|&&s| s).count() > 1 {
+            return Err(
+                "use only one of --level, --level-file, --generate, --endless and --replay"
+                    .to_owned(),
+            );
+        }
+        if self.difficulty.is_some() && self.generate.is_none() {
+            return Err("--difficulty only works with --generate".to_owned());
+        }
+        if self.replay.is_some() && self.seed.is_some() {
+            return Err("a replay brings its own seed, --seed can't change it".to_owned());
+        }
+        if self.ticks.is_some() && !self.headless {
+            return Err("--ticks only works with --headless".to_owned());
+        }
+        if self.headless && self.ticks.is_none() && self.replay.is_none() {
+            return Err("--headless needs --ticks <n>, or a --replay to run".to_owned());
+        }
+        Ok(())
+    }
+
+    /// How runs start, reading the level file if there is one.
+    pub fn setup(&self) -> Result<Setup, String> {
+        let course = if let Some(path) = &self.level_file {
+            let src = std::fs::read_to_string(path)
+                .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+            Level::parse(&src).map_err(|e| format!("{}: {e}", path.display()))?;
+            Course::Custom(src)
+        } else if let Some(seed) = self.generate {
+            let difficulty = self.difficulty.unwrap_or_default();
+            Course::Custom(generator::generate(seed, &difficulty).to_text())
+        } else if self.endless {
+            Course::Endless
+        } else {
+            Course::Campaign {
+                first: self.level.map_or(0, |n| n - 1),
+            }
+        };
+        Ok(Setup {
+            course,
+            seed: self.seed,
+        })
+    }
+
+    pub fn replay(&self) -> Result<Option<Replay>, String> {
+        let Some(path) = &self.replay else {
+            return Ok(None);
+        };
+        Replay::load(path)
+            .map(Some)
+            .map_err(|e| format!("could not load replay {}: {e}", path.display()))
+    }
+
+    /// `display` with the window options laid over it.
+    pub fn display(&self, display: &Display) -> Display {
+        Display {
+            width: self.width.unwrap_or(display.width),
+            height: self.height.unwrap_or(display.height),
+            fullscreen: self.fullscreen.unwrap_or(display.fullscreen),
+            fps_cap: self.fps_cap.unwrap_or(display.fps_cap),
+        }
+    }
+
+    /// The log level asked for, or what suits the mode: a headless run
+    /// keeps quiet so its summary stands out.
+    pub fn log_level(&self) -> log::LevelFilter {
+        self.log_level.unwrap_or(if self.headless {
+            log::LevelFilter::Warn
+        } else {
+            log::LevelFilter::Info
+        })
+    }
+
+    pub fn log_file(&self) -> PathBuf {
+        self.log_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("rayball.log"))
+    }
+}
+
+fn flag_takes_value(flag: &str) -> bool {
+    !matches!(
+        flag,
+        "--fullscreen" | "--windowed" | "--mute" | "--headless" | "--help" | "--endless"
+    )
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{flag} expects a number, not `{value}`"))
+}
+
+fn in_range(flag: &str, value: &str, (min, max): (u32, u32)) -> Result<u32, String> {
+    let n: u32 = parse(flag, value)?;
+    if !(min..=max).contains(&n) {
+        return Err(format!("{flag} must be between {min} and {max}"));
+    }
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_change_nothing() {
+        let options = parse_args(&[]).unwrap();
+        assert!(options.setup().unwrap().is_standard());
+        assert_eq!(options.display(&Display::default()), Display::default());
+        assert_eq!(options.log_level(), log::LevelFilter::Info);
+    }
+
+    #[test]
+    fn values_come_separate_or_inline() {
+        let options = parse_args(&["--seed", "42", "--width=1024", "--fps=0"]).unwrap();
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.width, Some(1024));
+        assert_eq!(options.fps_cap, Some(0));
+    }
+
+    #[test]
+    fn level_counts_from_one() {
+        let setup = parse_args(&["--level", "2"]).unwrap().setup().unwrap();
+        assert_eq!(setup.course, Course::Campaign { first: 1 });
+        assert!(parse_args(&["--level", "0"]).is_err());
+        assert!(parse_args(&["--level", "99"]).is_err());
+    }
+
+    #[test]
+    fn headless_needs_something_to_run() {
+        assert!(parse_args(&["--headless"]).is_err());
+        assert!(parse_args(&["--ticks", "10"]).is_err());
+        let options = parse_args(&["--headless", "--ticks", "10"]).unwrap();
+        assert_eq!(options.log_level(), log::LevelFilter::Warn);
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        assert!(parse_args(&["--bogus"]).is_err());
+        assert!(parse_args(&["--seed"]).is_err());
+        assert!(parse_args(&["--width", "10"]).is_err());
+        assert!(parse_args(&["--log-level", "loud"]).is_err());
+        assert!(parse_args(&["--mute=yes"]).is_err());
+        assert!(parse_args(&["--level", "1", "--replay", "a.replay"]).is_err());
+        assert!(parse_args(&["--generate", "1", "--level", "2"]).is_err());
+        assert!(parse_args(&["--difficulty", "hard"]).is_err());
+        assert!(parse_args(&["--endless", "--level-file", "a.lvl"]).is_err());
+        assert!(parse_args(&["--generate", "1", "--difficulty", "brutal"]).is_err());
+    }
+
+    #[test]
+    fn generated_levels_follow_the_seed() {
+        let setup = |args: &[&str]| parse_args(args).unwrap().setup().unwrap().course;
+        let easy = setup(&["--generate", "7", "--difficulty", "easy"]);
+        assert_eq!(easy, setup(&["--generate=7", "--difficulty=easy"]));
+        assert_ne!(easy, setup(&["--generate", "7", "--difficulty", "hard"]));
+        let Course::Custom(src) = easy else {
+            panic!("expected a custom course, got {easy:?}");
+        };
+        assert_eq!(Level::parse(&src).unwrap().name, "Seed 7");
+    }
+}
@@ -1,7 +1,10 @@
 use crate::constants::{BALL_RADIUS, VELOCITY};
 use macroquad::prelude::*;
 
-#[derive(PartialEq)]
+const LIVES_MARGIN: f32 = 30.;
+const LIVES_SPACING: f32 = BALL_RADIUS * 2.5;
+
+#[derive(Clone, Copy, PartialEq)]
 pub enum Status {
     Spawning,
     Start,
@@ -9,20 +12,40 @@ pub enum Status {
     Dead,
 }
 
+#[derive(Clone)]
 pub struct Ball {
     pub pos: Vec2,
+    /// Position at the start of the current tick, for interpolated drawing.
+    pub prev_pos: Vec2,
     pub velocity: Vec2,
     pub status: Status,
     pub radius: f32,
+    /// Smashes through breakable bricks instead of bouncing off them.
+    pub piercing: bool,
+    /// Sideways acceleration from a moving paddle, fading to nothing.
+    pub spin: f32,
+    /// Where the ball sits on the paddle while waiting to launch, from the
+    /// paddle's center. Only a ball the sticky paddle caught is off-center.
+    pub hold_offset: f32,
+}
+
+impl Default for Ball {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Ball {
     pub fn new() -> Self {
         Ball {
             pos: Vec2::ZERO,
+            prev_pos: Vec2::ZERO,
             velocity: Vec2::ZERO,
             status: Status::Start,
             radius: BALL_RADIUS,
+            piercing: false,
+            spin: 0.,
+            hold_offset: 0.,
         }
     }
 
@@ -30,17 +53,31 @@ impl Ball {
         if self.status != Status::Dead {
             self.status = Status::Dead;
             self.velocity = Vec2::ZERO;
+            self.spin = 0.;
         }
     }
 
     pub fn reset(&mut self) {
         self.status = Status::Start;
         self.velocity = Vec2::ZERO;
+        self.spin = 0.;
+        self.hold_offset = 0.;
         self.pos = Vec2::ZERO;
     }
 
     pub fn launch(&mut self) {
         self.velocity.y = -VELOCITY;
+        self.spin = 0.;
+        self.hold_offset = 0.;
         self.status = Status::Running;
     }
 }
+
+/// Where the HUD shows the `i`th remaining life. A respawned ball flies to the
+/// paddle from here.
+pub fn get_ball_lives_pos(i: u8) -> Vec2 {
+    vec2(
+        LIVES_MARGIN + ((i as f32 - 1.) * LIVES_SPACING),
+        LIVES_MARGIN,
+    )
+}
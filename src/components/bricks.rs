@@ -2,26 +2,56 @@ use macroquad::prelude::*;
 
 use crate::constants::BALL_RADIUS;
 
-const WIDTH: f32 = 60.;
-const HEIGHT: f32 = 20.;
+/// Size of a brick on the grid.
+pub const WIDTH: f32 = 60.;
+pub const HEIGHT: f32 = 20.;
 const SPACING: f32 = BALL_RADIUS;
+const START_Y: f32 = 100.;
+/// Distance from one grid row to the next.
+pub const ROW_PITCH: f32 = HEIGHT + SPACING;
 
+/// Color a multi-hit brick turns towards as it takes damage.
+const DAMAGE_TINT: Color = Color::new(0.85, 0.2, 0.15, 1.);
+
+/// Bricks within this distance (center to center) of an exploding brick are destroyed.
+pub const EXPLOSION_RADIUS: f32 = 90.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BrickKind {
+    /// Breaks after `hp` hits.
+    Normal,
+    /// Unbreakable, and not needed to clear the level.
+    Steel,
+    /// Destroys its neighbours when it breaks.
+    Explosive,
+}
+
+#[derive(Clone)]
 pub struct Brick {
     pub pos: Vec2,
     pub width: f32,
     pub height: f32,
     pub active: bool,
+    pub kind: BrickKind,
+    pub hp: u8,
+    pub max_hp: u8,
     pub color: Color,
+    /// Layout row, counted from the top.
+    pub row: usize,
 }
 
 impl Brick {
-    pub fn new(pos: Vec2, color: Color) -> Self {
+    pub fn new(pos: Vec2, kind: BrickKind, hp: u8, color: Color) -> Self {
         Brick {
             pos,
             width: WIDTH,
             height: HEIGHT,
             active: true,
+            kind,
+            hp,
+            max_hp: hp,
             color,
+            row: 0,
         }
     }
 
@@ -29,35 +59,95 @@ impl Brick {
         self.active = false;
     }
 
+    /// Takes one hit, returns true if that destroyed the brick.
+    pub fn hit(&mut self) -> bool {
+        if self.kind == BrickKind::Steel {
+            return false;
+        }
+        self.hp = self.hp.saturating_sub(1);
+        if self.hp == 0 {
+            self.die();
+            return true;
+        }
+        false
+    }
+
+    /// Score for destroying the brick, before any combo multiplier.
+    pub fn points(&self) -> u32 {
+        match self.kind {
+            BrickKind::Normal => 10 * self.max_hp as u32,
+            BrickKind::Steel => 0,
+            BrickKind::Explosive => 25,
+        }
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        self.kind != BrickKind::Steel
+    }
+
+    /// Base color shifted towards `DAMAGE_TINT`, and faded a little, as the
+    /// brick loses hit points.
+    pub fn display_color(&self) -> Color {
+        if self.max_hp <= 1 {
+            return self.color;
+        }
+        let damage = 1. - self.hp as f32 / self.max_hp as f32;
+        let blend = |from: f32, to: f32| from + (to - from) * damage * 0.8;
+        Color::new(
+            blend(self.color.r, DAMAGE_TINT.r),
+            blend(self.color.g, DAMAGE_TINT.g),
+            blend(self.color.b, DAMAGE_TINT.b),
+            self.color.a * (1. - 0.4 * damage),
+        )
+    }
+
     pub fn bound(&self) -> Rect {
         Rect::new(self.pos.x, self.pos.y, self.width, self.height)
     }
 
-    pub fn generate() -> Vec<Brick> {
-        // TODO: Generate by Levels, maybe parse strings
+    pub fn center(&self) -> Vec2 {
+        self.pos + vec2(self.width, self.height) / 2.
+    }
 
-        let max_col = (screen_width() / (WIDTH + SPACING)) as u8;
-        Brick::generate_simple(4, max_col)
+    /// Top-left corner of the brick at `row`/`col` in a grid `cols` wide,
+    /// centered horizontally in an arena `arena_width` wide.
+    pub fn grid_pos(row: usize, col: usize, cols: usize, arena_width: f32) -> Vec2 {
+        let total_width = (cols as f32 * WIDTH) + (cols.saturating_sub(1) as f32 * SPACING);
+        let start_x = (arena_width - total_width) / 2.0;
+        Vec2 {
+            x: start_x + col as f32 * (WIDTH + SPACING),
+            y: START_Y + row as f32 * ROW_PITCH,
+        }
     }
 
-    fn generate_simple(rows: u8, cols: u8) -> Vec<Brick> {
-        let mut bricks = Vec::new();
-        let start_y = 100.0;
+    /// Row and column of the grid slot `pos` falls in, the inverse of
+    /// `grid_pos`. Each slot reaches halfway across the gaps around its brick.
+    pub fn grid_cell(pos: Vec2, cols: usize, arena_width: f32) -> Option<(usize, usize)> {
+        let origin = Brick::grid_pos(0, 0, cols, arena_width) - Vec2::splat(SPACING / 2.);
+        let pitch = vec2(WIDTH + SPACING, ROW_PITCH);
+        let slot = ((pos - origin) / pitch).floor();
+        if slot.x < 0. || slot.y < 0. || slot.x >= cols as f32 {
+            return None;
+        }
+        Some((slot.y as usize, slot.x as usize))
+    }
+}
 
-        let total_width = (cols as f32 * WIDTH) + ((cols - 1) as f32 * SPACING);
-        let start_x = (screen_width() - total_width) / 2.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for r in 0..rows {
-            for c in 0..cols {
-                let pos = Vec2 {
-                    x: start_x + c as f32 * (WIDTH + SPACING),
-                    y: start_y + r as f32 * (HEIGHT + SPACING),
-                };
-                let mut color = WHITE;
-                color.a = 0.5;
-                bricks.push(Brick::new(pos, color));
-            }
-        }
-        bricks
+    #[test]
+    fn damage_shifts_the_color() {
+        let full = Brick::new(Vec2::ZERO, BrickKind::Normal, 3, Color::from_hex(0x8AADF4));
+        let mut damaged = Brick::new(Vec2::ZERO, BrickKind::Normal, 3, full.color);
+        damaged.hit();
+        damaged.hit();
+
+        assert_eq!(full.display_color(), full.color);
+        let (before, after) = (full.display_color(), damaged.display_color());
+        // A different hue, not just a fainter one
+        assert_ne!([before.r, before.g, before.b], [after.r, after.g, after.b]);
+        assert!(after.r > before.r && after.b < before.b);
     }
 }
@@ -2,8 +2,12 @@ pub mod ball;
 pub mod bricks;
 pub mod particle;
 pub mod platform;
+pub mod popup;
+pub mod powerup;
 
 pub use ball::{Ball, Status};
-pub use bricks::Brick;
+pub use bricks::{Brick, BrickKind};
 pub use particle::Particle;
 pub use platform::Platform;
+pub use popup::Popup;
+pub use powerup::{ActiveEffect, PowerUp, PowerUpKind};
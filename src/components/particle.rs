@@ -1,5 +1,7 @@
+use crate::rng::Rng;
 use macroquad::prelude::*;
 
+#[derive(Clone, Copy)]
 pub enum Direction {
     Left,
     Right,
@@ -20,23 +22,21 @@ impl Particle {
         self.life -= dt * 2.0; // Fade with time
     }
 
-    pub fn spawn_particles(origin: Vec2, direction: Direction) -> Vec<Particle> {
+    pub fn spawn_particles(origin: Vec2, direction: Direction, rng: &mut Rng) -> Vec<Particle> {
         let mut particles = Vec::new();
 
         for _ in 0..15 {
 
-            let positive = rand::gen_range(-200.0, 200.0);
-            let negative = rand::gen_range(-400.0, -100.0);
+            let positive = rng.range_f32(-200.0, 200.0);
+            let negative = rng.range_f32(-400.0, -100.0);
             let vel_up = Vec2::new(positive, negative);
             let vel_left = Vec2::new(negative, positive);
-            let vel: Vec2;
-
-            match direction {
-                Direction::Up => vel = vel_up,
-                Direction::Down => vel = vel_up * -1.,
-                Direction::Left => vel = vel_left,
-                Direction::Right => vel = vel_left * -1.,
-            }
+            let vel = match direction {
+                Direction::Up => vel_up,
+                Direction::Down => vel_up * -1.,
+                Direction::Left => vel_left,
+                Direction::Right => vel_left * -1.,
+            };
 
             let particle = Particle {
                 color: WHITE,
@@ -3,37 +3,71 @@ use macroquad::prelude::*;
 
 pub struct Platform {
     pub pos: Vec2,
+    /// Position at the start of the current tick, for interpolated drawing.
+    pub prev_pos: Vec2,
     pub width: f32,
     pub height: f32,
+    /// Sideways speed over the last tick, however the paddle was moved.
+    pub velocity: f32,
+    base_width: f32,
+    arena_width: f32,
+    /// Catches the ball instead of bouncing it.
+    pub sticky: bool,
 }
 
 impl Platform {
-    pub fn new() -> Self {
-        let width = screen_width() / 3.;
+    pub fn new(arena: Vec2) -> Self {
+        let width = arena.x / 3.;
         let height = PLATFORM_H;
         let pos = Vec2 {
-            x: (screen_width() - width) / 2.,
-            y: screen_height() - height,
+            x: (arena.x - width) / 2.,
+            y: arena.y - height,
         };
-        Platform { pos, width, height }
+        Platform {
+            pos,
+            prev_pos: pos,
+            width,
+            height,
+            velocity: 0.,
+            base_width: width,
+            arena_width: arena.x,
+            sticky: false,
+        }
+    }
+
+    /// Resizes around the current center, relative to the starting width.
+    pub fn set_width_scale(&mut self, scale: f32) {
+        let center = self.pos.x + self.width / 2.;
+        self.width = self.base_width * scale;
+        self.pos.x = (center - self.width / 2.).clamp(0., self.arena_width - self.width);
     }
 
     pub fn move_delta(&mut self, delta_x: f32) {
         self.pos.x += delta_x;
-        self.pos.x = self.pos.x.clamp(0., screen_width() - self.width);
+        self.pos.x = self.pos.x.clamp(0., self.arena_width - self.width);
+    }
+
+    /// Centers the paddle on `x`, as far as the walls allow.
+    pub fn move_to(&mut self, x: f32) {
+        self.pos.x = (x - self.width / 2.).clamp(0., self.arena_width - self.width);
+    }
+
+    /// Works out `velocity` from how far the paddle moved this tick.
+    pub fn track_velocity(&mut self, dt: f32) {
+        self.velocity = (self.pos.x - self.prev_pos.x) / dt;
     }
 
     pub fn move_left(&mut self, dt: f32) {
-        self.pos.x -= 1000. * dt;
+        self.pos.x -= PLATFORM_SPEED * dt;
         if self.pos.x < 0. {
             self.pos.x = 0.;
         }
     }
 
     pub fn move_right(&mut self, dt: f32) {
-        self.pos.x += 1000. * dt;
-        if self.pos.x + self.width > screen_width() {
-            self.pos.x = screen_width() - self.width;
+        self.pos.x += PLATFORM_SPEED * dt;
+        if self.pos.x + self.width > self.arena_width {
+            self.pos.x = self.arena_width - self.width;
         }
     }
 
@@ -0,0 +1,27 @@
+use macroquad::prelude::*;
+
+const RISE_SPEED: f32 = 60.;
+
+/// Floating text, like the "+50" shown where a brick was destroyed.
+pub struct Popup {
+    pub pos: Vec2,
+    pub text: String,
+    pub life: f32,
+    pub color: Color,
+}
+
+impl Popup {
+    pub fn new(pos: Vec2, text: String, color: Color) -> Self {
+        Popup {
+            pos,
+            text,
+            life: 1.0,
+            color,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.pos.y -= RISE_SPEED * dt;
+        self.life -= dt;
+    }
+}
@@ -0,0 +1,121 @@
+use crate::rng::Rng;
+use macroquad::prelude::*;
+
+const WIDTH: f32 = 40.;
+const HEIGHT: f32 = 16.;
+const FALL_SPEED: f32 = 200.;
+
+/// Chance a destroyed brick drops a capsule, unless the level sets `drops`.
+pub const DEFAULT_DROP_CHANCE: f32 = 0.15;
+
+/// How long a timed effect lasts once caught, in seconds.
+pub const EFFECT_DURATION: f32 = 10.;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerUpKind {
+    WidePaddle,
+    NarrowPaddle,
+    SlowBall,
+    FastBall,
+    ExtraLife,
+    StickyPaddle,
+    PiercingBall,
+    Multiball,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 8] = [
+        PowerUpKind::WidePaddle,
+        PowerUpKind::NarrowPaddle,
+        PowerUpKind::SlowBall,
+        PowerUpKind::FastBall,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::StickyPaddle,
+        PowerUpKind::PiercingBall,
+        PowerUpKind::Multiball,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::WidePaddle => "WIDE",
+            PowerUpKind::NarrowPaddle => "NARROW",
+            PowerUpKind::SlowBall => "SLOW",
+            PowerUpKind::FastBall => "FAST",
+            PowerUpKind::ExtraLife => "LIFE",
+            PowerUpKind::StickyPaddle => "STICKY",
+            PowerUpKind::PiercingBall => "PIERCE",
+            PowerUpKind::Multiball => "MULTI",
+        }
+    }
+
+    /// Short label printed on the capsule.
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerUpKind::WidePaddle => "W",
+            PowerUpKind::NarrowPaddle => "N",
+            PowerUpKind::SlowBall => "S",
+            PowerUpKind::FastBall => "F",
+            PowerUpKind::ExtraLife => "+",
+            PowerUpKind::StickyPaddle => "G",
+            PowerUpKind::PiercingBall => "P",
+            PowerUpKind::Multiball => "M",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::WidePaddle => GREEN,
+            PowerUpKind::NarrowPaddle => RED,
+            PowerUpKind::SlowBall => SKYBLUE,
+            PowerUpKind::FastBall => ORANGE,
+            PowerUpKind::ExtraLife => PINK,
+            PowerUpKind::StickyPaddle => YELLOW,
+            PowerUpKind::PiercingBall => VIOLET,
+            PowerUpKind::Multiball => GOLD,
+        }
+    }
+
+    /// Effects that can't be active together; catching one cancels the other.
+    pub fn opposite(self) -> Option<PowerUpKind> {
+        match self {
+            PowerUpKind::WidePaddle => Some(PowerUpKind::NarrowPaddle),
+            PowerUpKind::NarrowPaddle => Some(PowerUpKind::WidePaddle),
+            PowerUpKind::SlowBall => Some(PowerUpKind::FastBall),
+            PowerUpKind::FastBall => Some(PowerUpKind::SlowBall),
+            _ => None,
+        }
+    }
+}
+
+/// A capsule falling from a destroyed brick.
+pub struct PowerUp {
+    pub pos: Vec2,
+    pub kind: PowerUpKind,
+}
+
+impl PowerUp {
+    /// Rolls `drop_chance` for a capsule of random kind centered on `origin`.
+    pub fn roll_drop(origin: Vec2, drop_chance: f32, rng: &mut Rng) -> Option<PowerUp> {
+        if rng.next_f32() >= drop_chance {
+            return None;
+        }
+        let kind = PowerUpKind::ALL[rng.index(PowerUpKind::ALL.len())];
+        Some(PowerUp {
+            pos: origin - vec2(WIDTH, HEIGHT) / 2.,
+            kind,
+        })
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.pos.y += FALL_SPEED * dt;
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.pos.x, self.pos.y, WIDTH, HEIGHT)
+    }
+}
+
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub remaining: f32,
+}
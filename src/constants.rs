@@ -1,4 +1,14 @@
-use macroquad::prelude::Color;
+use macroquad::prelude::{Color, Vec2};
+
+/// Simulation steps per second, independent of the display rate.
+pub const TICK_RATE: f32 = 240.;
+pub const TICK_DT: f32 = 1. / TICK_RATE;
+/// Longest frame the simulation catches up on; anything beyond is dropped.
+pub const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Size of the playfield in logical units. It's scaled to fit the window, so
+/// layouts look the same at any resolution.
+pub const ARENA: Vec2 = Vec2::new(800., 600.);
 
 pub const BG_COLOR: Color = Color::new(23.0 / 255.0, 25.0 / 255.0, 29.0 / 255.0, 1.0);
 
@@ -7,5 +17,27 @@ pub const BALL_RADIUS: f32 = 15.0;
 
 // pub const PLATFORM_W: f32 = 300.;
 pub const PLATFORM_H: f32 = BALL_RADIUS;
+/// How fast the movement keys slide the paddle, per second.
+pub const PLATFORM_SPEED: f32 = 1000.;
+
+/// Share of the paddle's speed a ball picks up sideways when it bounces off.
+pub const ENGLISH: f32 = 0.3;
+/// Sideways acceleration a bounce off a moving paddle puts on the ball, per
+/// unit of paddle speed. It bends the ball's path for a moment.
+pub const CURVE: f32 = 0.8;
+/// How fast that acceleration dies down, per second.
+pub const SPIN_FADE: f32 = 1600.;
 
 pub const MAX_LIVES: u8 = 3;
+/// Extra life power-ups can't raise lives past this.
+pub const LIVES_CAP: u8 = 5;
+pub const MAX_BALLS: usize = 12;
+
+/// Brick hits in a row, without touching the paddle, per step of multiplier.
+pub const COMBO_STEP: u32 = 3;
+pub const MAX_MULTIPLIER: u32 = 8;
+/// Points per second a stage is cleared under its par time.
+pub const TIME_BONUS_PER_SECOND: u32 = 20;
+
+/// Seconds the "Level N" banner stays up when a stage starts.
+pub const LEVEL_INTRO_TIME: f32 = 2.0;
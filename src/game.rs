@@ -1,198 +1,277 @@
-use crate::components::particle::Direction;
-use crate::components::*;
-use crate::constants::MAX_LIVES;
-use crate::systems::audio::SoundManager;
-use crate::systems::physics;
-use crate::systems::render;
+use crate::playback::Playback;
 use macroquad::prelude::*;
+use rayball::constants::*;
+use rayball::input::{Action, Bindings, InputSampler, MouseConfig, TickInput};
+use rayball::levels::Course;
+use rayball::replay::{self, Outcome, Replay};
+use rayball::systems::audio::SoundManager;
+use rayball::systems::highscore::GameMode;
+use rayball::systems::render;
+use rayball::world::{GameEvent, World};
 
-pub enum GameEvent {
-    BallHitWall,
-    BallDropped,
-    BallHitPlatform(Vec2),
-    BrickCollision(Vec2, Direction),
+/// Where tick input comes from.
+enum Source {
+    /// The player, recorded as they play.
+    Live {
+        input: InputSampler,
+        recording: Replay,
+    },
+    Replay(Playback),
 }
 
-pub struct Game {
-    ball: Ball,
-    bricks: Vec<Brick>,
-    platform: Platform,
-    particles: Vec<Particle>,
-    sounds: SoundManager,
-    lives: u8,
-    death_pos: Vec<Vec2>,
-    won: bool,
-    last_touch_pos: Option<Vec2>,
-    start_touch_pos: Option<Vec2>,
+/// How new runs start, normally the campaign from stage one with a fresh
+/// seed; the command line can change either.
+#[derive(Clone, Default)]
+pub struct Setup {
+    pub course: Course,
+    pub seed: Option<u64>,
 }
 
-impl Game {
-    pub fn new(sounds: SoundManager) -> Self {
-        let mut game = Self {
-            ball: Ball::new(),
-            bricks: Brick::generate(),
-            platform: Platform::new(),
-            particles: Vec::new(),
-            sounds,
-            lives: MAX_LIVES,
-            death_pos: Vec::new(),
-            won: false,
-            last_touch_pos: None,
-            start_touch_pos: None,
-        };
+impl Setup {
+    /// Whether runs are the standard campaign, so scores are comparable.
+    pub fn is_standard(&self) -> bool {
+        self.course == Course::default() && self.seed.is_none()
+    }
 
-        game.sync_ball_position();
-        game
+    /// Whether a run in `mode` started from this setup goes on the high
+    /// score table. Endless runs are all alike, so only the seed matters.
+    pub fn ranks(&self, mode: GameMode) -> bool {
+        match mode {
+            GameMode::Campaign => self.is_standard(),
+            GameMode::Endless => self.seed.is_none(),
+        }
     }
 
-    pub fn update(&mut self) {
-        let dt = get_frame_time();
+    /// The same setup, playing endless mode.
+    pub fn endless(&self) -> Setup {
+        Setup {
+            course: Course::Endless,
+            seed: self.seed,
+        }
+    }
+}
 
-        self.won = !self.bricks.iter().any(|b| b.active);
+/// One run, from the first stage until the game ends or the player leaves it.
+pub struct Game {
+    pub world: World,
+    pub mode: GameMode,
+    /// Started from the editor, which it goes back to when it ends.
+    pub test_play: bool,
+    source: Source,
+    /// Frame time not yet simulated, always less than one tick once a frame's
+    /// ticks have run.
+    accumulator: f32,
+}
 
-        self.handle_keypress(dt);
-        self.handle_touches();
+impl Game {
+    pub fn new(setup: &Setup) -> Self {
+        let seed = setup
+            .seed
+            .unwrap_or_else(|| (miniquad::date::now() * 1000.) as u64);
+        log::info!("Simulation seed {seed}");
+        let mut recording = Replay::new(seed, ARENA);
+        recording.course = setup.course.clone();
+        let world = recording.world();
+        let source = Source::Live {
+            input: InputSampler::default(),
+            recording,
+        };
+        Self::with_source(world, source)
+    }
 
-        self.move_ball(dt);
+    /// A run of a level from the editor, on a fresh seed.
+    pub fn test_play(course: Course) -> Self {
+        let setup = Setup { course, seed: None };
+        Game {
+            test_play: true,
+            ..Game::new(&setup)
+        }
+    }
 
-        self.particles.iter_mut().for_each(|p| p.update(dt));
-        self.particles.retain(|p| p.life > 0.0);
+    /// Watches `replay` instead of playing.
+    pub fn replay(replay: Replay) -> Self {
+        log::info!(
+            "Playing back replay with seed {} ({} ticks)",
+            replay.seed,
+            replay.inputs.len()
+        );
+        let world = replay.world();
+        Self::with_source(world, Source::Replay(Playback::new(replay)))
     }
 
-    fn handle_keypress(&mut self, dt: f32) {
-        if is_key_down(KeyCode::Left) {
-            self.platform.move_left(dt);
-        }
-        if is_key_down(KeyCode::Right) {
-            self.platform.move_right(dt);
-        }
-        if is_key_pressed(KeyCode::Space) {
-            self.handle_launch_input();
+    fn with_source(world: World, source: Source) -> Self {
+        let mode = match world.course {
+            Course::Endless => GameMode::Endless,
+            _ => GameMode::Campaign,
+        };
+        Self {
+            world,
+            mode,
+            test_play: false,
+            source,
+            accumulator: 0.,
         }
     }
 
-    fn handle_touches(&mut self) {
-        if let Some(touch) = touches().first() {
-            match touch.phase {
-                TouchPhase::Started => {
-                    self.last_touch_pos = Some(touch.position);
-                    self.start_touch_pos = Some(touch.position);
-                }
-                TouchPhase::Moved => {
-                    if let Some(last_pos) = self.last_touch_pos {
-                        let delta_x = touch.position.x - last_pos.x;
-                        self.platform.move_delta(delta_x);
-                    }
-                    self.last_touch_pos = Some(touch.position);
-                }
-                TouchPhase::Ended => {
-                    // Check if this was a "Tap" (finger didn't move much)
-                    if let Some(start_pos) = self.start_touch_pos {
-                        let distance = (touch.position - start_pos).length();
-
-                        if distance < 10.0 {
-                            if self.won {
-                                self.reset_game();
-                                return;
-                            }
-                            self.handle_launch_input();
-                        }
-                    }
-                    self.last_touch_pos = None;
-                    self.start_touch_pos = None;
-                }
-                _ => {}
+    /// A fresh run of the same kind: a new game on the same course, or the
+    /// replay from the start.
+    pub fn restart(&self, setup: &Setup) -> Self {
+        match &self.source {
+            Source::Live { recording, .. } if self.test_play => {
+                Game::test_play(recording.course.clone())
             }
+            Source::Live { recording, .. } => Game::new(&Setup {
+                course: recording.course.clone(),
+                seed: setup.seed,
+            }),
+            Source::Replay(playback) => Game::replay(playback.replay.clone()),
         }
     }
 
-    pub fn draw(&self) {
-        render::draw_world(&self.ball, &self.bricks, &self.platform, &self.particles);
-        render::draw_game_ui(self.lives, &self.ball.status, &self.death_pos, self.won);
+    pub fn is_replay(&self) -> bool {
+        matches!(self.source, Source::Replay(_))
     }
 
-    fn move_ball(&mut self, dt: f32) {
-        match self.ball.status {
-            Status::Start => {
-                physics::snap_ball_to_platform(&mut self.ball, &self.platform);
-            }
-            Status::Running => {
-                let mut events = Vec::new();
-                events.extend(physics::update_ball_position(&mut self.ball, dt));
-                events.extend(physics::resolve_ball_collisions(
-                    &mut self.ball,
-                    &self.platform,
-                    &mut self.bricks,
-                ));
-                for event in events {
-                    self.handle_event(event);
-                }
-            }
-            Status::Dead => {}
-            Status::Spawning => {
-                if physics::transition_ball(&mut self.ball, &self.platform, dt) {
-                    return;
-                }
-                self.ball.status = Status::Start;
-                self.sync_ball_position();
+    /// Runs as many fixed ticks as the frame time covers and returns what
+    /// happened. Stops early at the end of a stage or of the game, so the
+    /// screen that follows sees the world as it was then.
+    pub fn update(
+        &mut self,
+        sounds: &mut SoundManager,
+        bindings: &Bindings,
+        mouse: MouseConfig,
+    ) -> Vec<GameEvent> {
+        let frame_time = get_frame_time().min(MAX_FRAME_TIME);
+        let mut events = Vec::new();
+
+        if let Source::Replay(playback) = &mut self.source {
+            playback.handle_keys(bindings);
+            for input in playback.advance(frame_time, &mut self.accumulator) {
+                events.extend(self.tick(input, sounds));
             }
+            return events;
         }
-    }
 
-    fn handle_event(&mut self, event: GameEvent) {
-        match event {
-            GameEvent::BallDropped => {
-                self.death_pos.push(self.ball.pos);
-                self.lives -= 1;
-                if self.lives > 0 {
-                    self.ball.reset();
-                    self.ball.pos = render::get_ball_lives_pos(self.lives);
-                    self.ball.status = Status::Spawning;
-                    self.sounds.play_transition();
-                }
-            }
-            GameEvent::BallHitWall => {
-                self.sounds.play_bounce();
-            }
-            GameEvent::BallHitPlatform(hit_point) => {
-                self.particles.extend(Particle::spawn_particles(
-                    hit_point,
-                    particle::Direction::Up,
-                ));
-                self.sounds.play_bounce();
+        let Source::Live { input, .. } = &mut self.source else {
+            return events;
+        };
+        let platform = &self.world.platform;
+        input.sample(bindings, mouse, platform.pos.x + platform.width / 2.);
+        self.accumulator += frame_time;
+        while self.accumulator >= TICK_DT {
+            let Source::Live { input, recording } = &mut self.source else {
+                break;
+            };
+            // Nothing is played or recorded past the end of the game
+            if self.world.is_over() {
+                self.accumulator %= TICK_DT;
+                break;
             }
-            GameEvent::BrickCollision(hit_point, direction) => {
-                self.particles
-                    .extend(Particle::spawn_particles(hit_point, direction));
-                self.sounds.play_bounce();
+            let input = input.next_tick();
+            recording.inputs.push(input);
+            self.accumulator -= TICK_DT;
+
+            let tick_events = self.tick(input, sounds);
+            let milestone = tick_events.iter().any(|e| {
+                matches!(
+                    e,
+                    GameEvent::StageStarted(_) | GameEvent::GameOver | GameEvent::GameWon
+                )
+            });
+            events.extend(tick_events);
+            if milestone {
+                // Whole ticks still owed are dropped, the next scene starts
+                // fresh and drawing must not interpolate past the last tick
+                self.accumulator %= TICK_DT;
+                break;
             }
         }
+        events
     }
 
-    fn sync_ball_position(&mut self) {
-        physics::snap_ball_to_platform(&mut self.ball, &self.platform);
+    /// Writes the session so far to the replay folder, if this is a live game.
+    pub fn save_recording(&mut self) {
+        let Source::Live { recording, .. } = &mut self.source else {
+            return;
+        };
+        recording.expected = Some(Outcome::of(&self.world));
+        let Some(path) = replay::recording_path(recording.seed) else {
+            return;
+        };
+        match recording.save(&path) {
+            Ok(()) => log::info!("Saved replay to {}", path.display()),
+            Err(e) => log::warn!("Could not save replay to {}: {e}", path.display()),
+        }
     }
 
-    fn reset_game(&mut self) {
-        self.lives = MAX_LIVES;
-        self.death_pos = Vec::new();
-        self.ball.reset();
-        self.platform = Platform::new();
-        self.sync_ball_position();
-        for b in &mut self.bricks {
-            b.active = true;
+    fn tick(&mut self, input: TickInput, sounds: &mut SoundManager) -> Vec<GameEvent> {
+        self.world.tick(input);
+        let events = self.world.drain_events();
+        for event in &events {
+            match event {
+                GameEvent::BallHitWall
+                | GameEvent::BallHitPlatform(_)
+                | GameEvent::BrickCollision(..)
+                | GameEvent::BrickExploded(_) => sounds.play_bounce(),
+                GameEvent::PowerUpCaught(_)
+                | GameEvent::BallRespawned
+                | GameEvent::StageStarted(_)
+                | GameEvent::RowAdded => sounds.play_transition(),
+                _ => {}
+            }
         }
+        events
     }
 
-    fn handle_launch_input(&mut self) {
-        if self.won {
-            self.reset_game();
-            return;
+    pub fn draw(&self, bindings: &Bindings) {
+        let world = &self.world;
+        // How far between the last tick and the next one this frame falls
+        let alpha = self.accumulator / TICK_DT;
+        render::draw_world(
+            &world.balls,
+            world.bricks(),
+            &world.platform,
+            &world.particles,
+            &world.powerups,
+            alpha,
+        );
+        render::draw_popups(&world.popups);
+        render::draw_game_ui(
+            world.lives,
+            &world.ball_status(),
+            &world.death_pos,
+            &bindings.label(Action::Launch),
+        );
+        // Combos don't score in endless mode
+        let multiplier = match world.descent {
+            Some(_) => 1,
+            None => world.multiplier(),
+        };
+        render::draw_score(world.score, multiplier);
+        render::draw_effects(&world.effects);
+        if let Some(descent) = &world.descent {
+            render::draw_descent(descent, world.platform.pos.y);
+        }
+        if world.intro_timer > 0. {
+            let alpha = (world.intro_timer / LEVEL_INTRO_TIME * 2.).min(1.);
+            render::draw_level_intro(world.stage + 1, &world.stage_name, alpha);
         }
-        match self.ball.status {
-            Status::Start => self.ball.launch(),
-            Status::Dead if self.lives == 0 => self.reset_game(),
-            _ => {}
+        if let Source::Replay(playback) = &self.source {
+            // The pause key opens the pause menu, as it does in play
+            let hint = format!(
+                "{} PAUSE   {} FAST-FORWARD   {} STEP   {} MENU",
+                bindings.label(Action::ReplayPause),
+                bindings.label(Action::ReplaySpeed),
+                bindings.label(Action::ReplayStep),
+                bindings.label(Action::Pause),
+            );
+            render::draw_replay_status(
+                playback.tick,
+                playback.replay.inputs.len(),
+                playback.speed(),
+                playback.paused,
+                &hint,
+            );
         }
     }
 }
@@ -0,0 +1,107 @@
+//! Runs the simulation without a window, for scripts and benchmarks.
+
+use crate::game::Setup;
+use macroquad::prelude::*;
+use rayball::components::Status;
+use rayball::constants::{ARENA, TICK_RATE};
+use rayball::input::TickInput;
+use rayball::levels::Course;
+use rayball::replay::{Outcome, Replay};
+use rayball::world::{GameEvent, World};
+use std::process::ExitCode;
+use std::time::Instant;
+
+/// Runs up to `ticks` ticks, of the replay's input if there is one or of a
+/// simple autopilot otherwise, and prints how the run went. A replay runs to
+/// its end unless `ticks` says otherwise. Stops early when the game ends.
+pub fn run(setup: &Setup, replay: Option<Replay>, ticks: Option<u64>) -> ExitCode {
+    let (mut world, inputs, seed) = match replay {
+        Some(replay) => (replay.world(), replay.inputs, replay.seed),
+        None => {
+            let seed = setup
+                .seed
+                .unwrap_or_else(|| (miniquad::date::now() * 1000.) as u64);
+            let world = World::with_course(ARENA, seed, setup.course.clone());
+            (world, Vec::new(), seed)
+        }
+    };
+    let ticks = ticks.unwrap_or(inputs.len() as u64);
+    let autopilot = inputs.is_empty();
+
+    let started = Instant::now();
+    let mut balls_lost = 0;
+    let mut ended = None;
+    for tick in 0..ticks {
+        let input = match inputs.get(tick as usize) {
+            Some(&input) => input,
+            None if autopilot => autopilot_input(&world, tick),
+            None => TickInput::default(),
+        };
+        world.tick(input);
+        for event in world.drain_events() {
+            match event {
+                GameEvent::BallDropped(_) => balls_lost += 1,
+                GameEvent::GameOver => ended = Some("game over"),
+                GameEvent::GameWon => ended = Some("won"),
+                _ => {}
+            }
+        }
+        if ended.is_some() {
+            break;
+        }
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let outcome = Outcome::of(&world);
+    let played = world.ticks;
+    let course = match &world.course {
+        Course::Campaign { first } => format!("campaign from stage {}", first + 1),
+        Course::Custom(_) => "custom level".to_owned(),
+        Course::Endless => "endless".to_owned(),
+    };
+    let left = world
+        .bricks()
+        .iter()
+        .filter(|b| b.active && b.is_breakable())
+        .count();
+    let breakable = world.bricks().iter().filter(|b| b.is_breakable()).count();
+
+    println!("seed     {seed}");
+    println!("course   {course}");
+    println!(
+        "ticks    {played} ({:.1} s of play) in {:.3} s, {:.0} ticks/s",
+        played as f32 / TICK_RATE,
+        elapsed,
+        played as f64 / elapsed.max(f64::EPSILON)
+    );
+    println!("result   {}", ended.unwrap_or("still playing"));
+    println!("score    {}", outcome.score);
+    println!("lives    {}", outcome.lives);
+    println!("lost     {balls_lost} balls");
+    println!("stage    {} \"{}\"", outcome.stage + 1, world.stage_name);
+    println!("bricks   {left}/{breakable} left");
+    if let Some(descent) = &world.descent {
+        println!("rows     {} survived", descent.rows);
+    }
+    println!("hash     {:016x}", outcome.hash);
+    ExitCode::SUCCESS
+}
+
+/// Launches whenever a ball waits on the paddle and keeps the paddle under
+/// the lowest ball, off center by an amount that drifts over time so the
+/// ball doesn't settle into one path.
+fn autopilot_input(world: &World, tick: u64) -> TickInput {
+    let lowest = world
+        .balls
+        .iter()
+        .filter(|b| b.status == Status::Running)
+        .max_by(|a, b| a.pos.y.total_cmp(&b.pos.y));
+    let platform = &world.platform;
+    let center = platform.pos.x + platform.width / 2.;
+    let offset = (tick as f32 / 500.).sin() * platform.width * 0.3;
+    TickInput {
+        launch: world.balls.iter().any(|b| b.status == Status::Start),
+        pointer: Some(lowest.map_or(center, |b| b.pos.x + offset)),
+        ..Default::default()
+    }
+}
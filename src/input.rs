@@ -0,0 +1,659 @@
+use crate::constants::{ARENA, TICK_DT};
+use crate::settings::short_float;
+use crate::systems::view;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const HEADER: &str = "# rayball controls v1";
+
+/// Something the player can do, bound to one or more keys.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Launch,
+    Pause,
+    MenuUp,
+    MenuDown,
+    Confirm,
+    Back,
+    ReplayPause,
+    ReplaySpeed,
+    ReplayStep,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Launch,
+        Action::Pause,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::Confirm,
+        Action::Back,
+        Action::ReplayPause,
+        Action::ReplaySpeed,
+        Action::ReplayStep,
+    ];
+
+    /// Name used for the action in the controls file.
+    fn key(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Launch => "launch",
+            Action::Pause => "pause",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::ReplayPause => "replay_pause",
+            Action::ReplaySpeed => "replay_speed",
+            Action::ReplayStep => "replay_step",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.key() == key)
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Launch => "LAUNCH",
+            Action::Pause => "PAUSE",
+            Action::MenuUp => "MENU UP",
+            Action::MenuDown => "MENU DOWN",
+            Action::Confirm => "CONFIRM",
+            Action::Back => "BACK",
+            Action::ReplayPause => "REPLAY PAUSE",
+            Action::ReplaySpeed => "REPLAY SPEED",
+            Action::ReplayStep => "REPLAY STEP",
+        }
+    }
+
+    /// Actions are only ever read in play or in menus, never both, so keys
+    /// only conflict between actions of the same kind.
+    fn in_menus(self) -> bool {
+        matches!(
+            self,
+            Action::MenuUp | Action::MenuDown | Action::Confirm | Action::Back
+        )
+    }
+
+    fn default_keys(self) -> Vec<KeyCode> {
+        match self {
+            Action::MoveLeft => vec![KeyCode::Left, KeyCode::A],
+            Action::MoveRight => vec![KeyCode::Right, KeyCode::D],
+            Action::Launch => vec![KeyCode::Space],
+            Action::Pause => vec![KeyCode::Escape],
+            Action::MenuUp => vec![KeyCode::Up, KeyCode::W],
+            Action::MenuDown => vec![KeyCode::Down, KeyCode::S],
+            Action::Confirm => vec![KeyCode::Enter, KeyCode::Space],
+            Action::Back => vec![KeyCode::Escape],
+            Action::ReplayPause => vec![KeyCode::P],
+            Action::ReplaySpeed => vec![KeyCode::F],
+            Action::ReplayStep => vec![KeyCode::Period],
+        }
+    }
+}
+
+/// Keys that can be bound, named in the controls file as they are in `KeyCode`.
+const BINDABLE_KEYS: [KeyCode; 90] = {
+    use KeyCode::*;
+    [
+        Space,
+        Apostrophe,
+        Comma,
+        Minus,
+        Period,
+        Slash,
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        Semicolon,
+        Equal,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        LeftBracket,
+        Backslash,
+        RightBracket,
+        GraveAccent,
+        Escape,
+        Enter,
+        Tab,
+        Backspace,
+        Insert,
+        Delete,
+        Right,
+        Left,
+        Down,
+        Up,
+        PageUp,
+        PageDown,
+        Home,
+        End,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Kp0,
+        Kp1,
+        Kp2,
+        Kp3,
+        Kp4,
+        Kp5,
+        Kp6,
+        Kp7,
+        Kp8,
+        Kp9,
+        KpEnter,
+        LeftShift,
+        LeftControl,
+        LeftAlt,
+        RightShift,
+        RightControl,
+    ]
+};
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{key:?}")
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|&k| key_name(k) == name)
+}
+
+/// Which keys trigger each action. Loaded from and saved to the platform
+/// config directory on native builds.
+pub struct Bindings {
+    path: Option<PathBuf>,
+    keys: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Bindings {
+    pub fn defaults() -> Self {
+        Bindings {
+            path: None,
+            keys: Action::ALL.iter().map(|&a| (a, a.default_keys())).collect(),
+        }
+    }
+
+    /// Actions the file leaves out keep their default keys. A file binding one
+    /// key to two actions that clash is ignored altogether.
+    pub fn load() -> Self {
+        let bindings = Bindings {
+            path: bindings_path(),
+            ..Bindings::defaults()
+        };
+        let Some(path) = bindings.path.clone() else {
+            return bindings;
+        };
+        let src = match std::fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return bindings,
+            Err(e) => {
+                log::warn!("Could not read controls from {}: {e}", path.display());
+                return bindings;
+            }
+        };
+
+        let parsed = parse(&src).and_then(|parsed| {
+            let mut loaded = Bindings {
+                path: bindings.path.clone(),
+                ..Bindings::defaults()
+            };
+            for (action, keys) in parsed {
+                loaded.set(action, keys);
+            }
+            match loaded.first_conflict() {
+                Some((key, a, b)) => Err(format!(
+                    "{} is bound to both `{}` and `{}`",
+                    key_name(key),
+                    a.key(),
+                    b.key()
+                )),
+                None => Ok(loaded),
+            }
+        });
+        match parsed {
+            Ok(loaded) => {
+                log::info!("Loaded controls from {}", path.display());
+                loaded
+            }
+            Err(e) => {
+                log::warn!("Ignoring broken controls file {}: {e}", path.display());
+                bindings
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let out = self.to_text();
+
+        if let Some(dir) = path.parent()
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            log::warn!("Could not create {}: {e}", dir.display());
+            return;
+        }
+        match std::fs::write(path, out) {
+            Ok(()) => log::info!("Saved controls to {}", path.display()),
+            Err(e) => log::warn!("Could not save controls to {}: {e}", path.display()),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::from(HEADER);
+        out.push('\n');
+        for (action, keys) in &self.keys {
+            let names: Vec<String> = keys.iter().map(|&k| key_name(k)).collect();
+            out.push_str(&format!("{} = {}\n", action.key(), names.join(", ")));
+        }
+        out
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, action: Action, keys: Vec<KeyCode>) {
+        match self.keys.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, bound)) => *bound = keys,
+            None => self.keys.push((action, keys)),
+        }
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.set(action, action.default_keys());
+    }
+
+    pub fn reset_all(&mut self) {
+        for action in Action::ALL {
+            self.reset(action);
+        }
+    }
+
+    /// Another action `key` can't also be bound to, if there is one.
+    pub fn conflict(&self, action: Action, key: KeyCode) -> Option<Action> {
+        Action::ALL.into_iter().find(|&other| {
+            other != action
+                && other.in_menus() == action.in_menus()
+                && self.keys(other).contains(&key)
+        })
+    }
+
+    fn first_conflict(&self) -> Option<(KeyCode, Action, Action)> {
+        Action::ALL.into_iter().find_map(|action| {
+            self.keys(action)
+                .iter()
+                .find_map(|&key| self.conflict(action, key).map(|other| (key, action, other)))
+        })
+    }
+
+    /// Binds `key` to `action`, or unbinds it if it already was. Fails with
+    /// the action in the way if another one uses the key, or if this would
+    /// leave `action` with no keys at all.
+    pub fn toggle(&mut self, action: Action, key: KeyCode) -> Result<(), String> {
+        let mut keys = self.keys(action).to_vec();
+        if let Some(i) = keys.iter().position(|&k| k == key) {
+            if keys.len() == 1 {
+                return Err(format!("{} NEEDS AT LEAST ONE KEY", action.title()));
+            }
+            keys.remove(i);
+        } else {
+            if !BINDABLE_KEYS.contains(&key) {
+                return Err(format!("{} CAN'T BE BOUND", key_name(key).to_uppercase()));
+            }
+            if let Some(other) = self.conflict(action, key) {
+                return Err(format!(
+                    "{} IS ALREADY USED FOR {}",
+                    key_name(key).to_uppercase(),
+                    other.title()
+                ));
+            }
+            keys.push(key);
+        }
+        self.set(action, keys);
+        Ok(())
+    }
+
+    /// The first key bound to `action`, named as prompts show it.
+    pub fn label(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map(|&k| key_name(k).to_uppercase())
+            .unwrap_or_default()
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&k| is_key_down(k))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&k| is_key_pressed(k))
+    }
+}
+
+/// Reads `action = Key, Key` lines.
+fn parse(src: &str) -> Result<Vec<(Action, Vec<KeyCode>)>, String> {
+    let mut lines = src.lines();
+    if lines.next().map(str::trim) != Some(HEADER) {
+        return Err("missing or unknown header".to_owned());
+    }
+
+    let mut parsed = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line_no = i + 2;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (action, keys) = line
+            .split_once('=')
+            .ok_or(format!("line {line_no}: expected `action = keys`"))?;
+        let action = action.trim();
+        let action =
+            Action::from_key(action).ok_or(format!("line {line_no}: unknown action `{action}`"))?;
+        let keys = keys
+            .split(',')
+            .map(|name| {
+                let name = name.trim();
+                key_from_name(name).ok_or(format!("line {line_no}: unknown key `{name}`"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(format!("line {line_no}: `{}` has no keys", action.key()));
+        }
+        parsed.push((action, keys));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn bindings_path() -> Option<PathBuf> {
+    let path = dirs::config_dir().map(|d| d.join("rayball").join("controls.txt"));
+    if path.is_none() {
+        log::warn!("No config directory found, controls won't be saved");
+    }
+    path
+}
+
+#[cfg(target_arch = "wasm32")]
+fn bindings_path() -> Option<PathBuf> {
+    None
+}
+
+/// Input applied to one simulation tick.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct TickInput {
+    pub left: bool,
+    pub right: bool,
+    /// Launch the ball, or restart once the game is over.
+    pub launch: bool,
+    /// Horizontal paddle drag in arena units, from touch.
+    pub drag: f32,
+    /// Where the mouse wants the paddle's center, in arena coordinates.
+    pub pointer: Option<f32>,
+}
+
+/// How the mouse steers the paddle, when it does.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseConfig {
+    /// The paddle follows the mouse instead of the movement keys.
+    pub enabled: bool,
+    /// Paddle distance per unit of mouse movement.
+    #[serde(serialize_with = "short_float")]
+    pub sensitivity: f32,
+    /// How much the paddle lags behind the cursor, from 0 (not at all) to
+    /// just under 1.
+    #[serde(serialize_with = "short_float")]
+    pub smoothing: f32,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        MouseConfig {
+            enabled: false,
+            sensitivity: 1.,
+            smoothing: 0.3,
+        }
+    }
+}
+
+/// Samples keyboard and touch once per frame and hands the result out one
+/// tick at a time. Presses and drags are held until a tick consumes them, so
+/// nothing is lost on frames short enough to run no ticks at all.
+#[derive(Default)]
+pub struct InputSampler {
+    pending: TickInput,
+    last_touch_pos: Option<Vec2>,
+    start_touch_pos: Option<Vec2>,
+    mouse: MouseConfig,
+    /// Where the mouse has moved the paddle to, before smoothing. Unset while
+    /// the movement keys are steering.
+    cursor_x: Option<f32>,
+    /// Paddle target handed to the ticks, easing towards `cursor_x`.
+    smoothed_x: Option<f32>,
+}
+
+impl InputSampler {
+    /// `paddle_x` is the paddle's center, where the mouse takes over from.
+    pub fn sample(&mut self, bindings: &Bindings, mouse: MouseConfig, paddle_x: f32) {
+        self.pending.left = bindings.down(Action::MoveLeft);
+        self.pending.right = bindings.down(Action::MoveRight);
+        if bindings.pressed(Action::Launch) {
+            self.pending.launch = true;
+        }
+        self.sample_touches();
+        self.mouse = mouse;
+        if mouse.enabled {
+            if is_mouse_button_pressed(MouseButton::Left) {
+                self.pending.launch = true;
+            }
+            let keys = self.pending.left || self.pending.right;
+            self.steer(view::mouse_delta().x, keys, paddle_x);
+        } else {
+            self.cursor_x = None;
+            self.smoothed_x = None;
+        }
+    }
+
+    /// Input for the next tick. Held keys repeat, one-off presses don't.
+    pub fn next_tick(&mut self) -> TickInput {
+        if let Some(target) = self.cursor_x {
+            let x = match self.smoothed_x {
+                Some(x) => x + (target - x) * follow_per_tick(self.mouse.smoothing),
+                None => target,
+            };
+            self.smoothed_x = Some(x);
+            self.pending.pointer = Some(x);
+        } else {
+            self.pending.pointer = None;
+        }
+        let input = self.pending;
+        self.pending.launch = false;
+        self.pending.drag = 0.;
+        input
+    }
+
+    /// Moves the virtual cursor `moved` arena units, scaled by the
+    /// sensitivity. The mouse takes over from wherever the paddle is once it
+    /// moves, and hands back to the keys while either is held.
+    fn steer(&mut self, moved: f32, keys: bool, paddle_x: f32) {
+        if keys {
+            self.cursor_x = None;
+            self.smoothed_x = None;
+            return;
+        }
+        let from = match self.cursor_x {
+            Some(x) => x,
+            None if moved != 0. => {
+                self.smoothed_x = None;
+                paddle_x
+            }
+            None => return,
+        };
+        let x = from + moved * self.mouse.sensitivity;
+        self.cursor_x = Some(x.clamp(0., ARENA.x));
+    }
+
+    fn sample_touches(&mut self) {
+        if let Some(touch) = touches().first() {
+            let position = view::touch_position(touch);
+            match touch.phase {
+                TouchPhase::Started => {
+                    self.last_touch_pos = Some(position);
+                    self.start_touch_pos = Some(position);
+                }
+                TouchPhase::Moved => {
+                    if let Some(last_pos) = self.last_touch_pos {
+                        self.pending.drag += position.x - last_pos.x;
+                    }
+                    self.last_touch_pos = Some(position);
+                }
+                TouchPhase::Ended => {
+                    // Check if this was a "Tap" (finger didn't move much)
+                    if let Some(start_pos) = self.start_touch_pos {
+                        let distance = (position - start_pos).length();
+
+                        if distance < 10.0 {
+                            self.pending.launch = true;
+                        }
+                    }
+                    self.last_touch_pos = None;
+                    self.start_touch_pos = None;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Fraction of the way to the cursor the paddle covers in one tick. The
+/// smoothing is what's left of the distance after a 60th of a second, so it
+/// feels the same at any tick rate.
+fn follow_per_tick(smoothing: f32) -> f32 {
+    1. - smoothing.clamp(0., 0.95).powf(TICK_DT * 60.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_have_no_conflicts() {
+        assert_eq!(Bindings::defaults().first_conflict(), None);
+    }
+
+    #[test]
+    fn menu_and_play_actions_can_share_keys() {
+        let bindings = Bindings::defaults();
+        assert!(bindings.keys(Action::Launch).contains(&KeyCode::Space));
+        assert!(bindings.keys(Action::Confirm).contains(&KeyCode::Space));
+    }
+
+    #[test]
+    fn binding_a_key_used_by_another_action_fails() {
+        let mut bindings = Bindings::defaults();
+        let err = bindings.toggle(Action::Launch, KeyCode::A).unwrap_err();
+        assert!(err.contains("MOVE LEFT"), "{err}");
+        assert_eq!(bindings.keys(Action::Launch), [KeyCode::Space]);
+    }
+
+    #[test]
+    fn toggling_adds_then_removes() {
+        let mut bindings = Bindings::defaults();
+        bindings.toggle(Action::Launch, KeyCode::Up).unwrap();
+        assert_eq!(bindings.keys(Action::Launch), [KeyCode::Space, KeyCode::Up]);
+        bindings.toggle(Action::Launch, KeyCode::Space).unwrap();
+        assert_eq!(bindings.keys(Action::Launch), [KeyCode::Up]);
+        assert!(bindings.toggle(Action::Launch, KeyCode::Up).is_err());
+    }
+
+    #[test]
+    fn file_round_trips() {
+        let mut bindings = Bindings::defaults();
+        bindings.toggle(Action::Pause, KeyCode::Q).unwrap();
+        let parsed = parse(&bindings.to_text()).unwrap();
+        for (action, keys) in parsed {
+            assert_eq!(bindings.keys(action), keys.as_slice());
+        }
+    }
+
+    #[test]
+    fn the_mouse_steers_from_the_paddle_until_a_key_is_held() {
+        let mut sampler = InputSampler::default();
+        sampler.steer(0., false, 300.);
+        assert_eq!(sampler.next_tick().pointer, None);
+
+        sampler.steer(10., false, 300.);
+        assert_eq!(sampler.cursor_x, Some(310.));
+        assert_eq!(sampler.next_tick().pointer, Some(310.));
+
+        sampler.steer(5., true, 310.);
+        assert_eq!(sampler.next_tick().pointer, None);
+        // Picks up from where the keys left the paddle
+        sampler.steer(-10., false, 500.);
+        assert_eq!(sampler.next_tick().pointer, Some(490.));
+    }
+
+    #[test]
+    fn unknown_keys_are_reported_with_their_line() {
+        let src = format!("{HEADER}\nlaunch = Space\nmove_left = Left, Banana\n");
+        let err = parse(&src).unwrap_err();
+        assert!(err.starts_with("line 3"), "{err}");
+        assert!(err.contains("Banana"), "{err}");
+    }
+}
@@ -0,0 +1,216 @@
+use super::parser::{Cell, Level, Pace};
+use crate::components::powerup::DEFAULT_DROP_CHANCE;
+
+/// Columns in the editor's grid, as many bricks as fit across the arena.
+pub const COLS: usize = 10;
+/// Rows in the editor's grid, leaving the ball room above the paddle.
+pub const ROWS: usize = 10;
+/// Edits kept for undoing.
+const HISTORY: usize = 100;
+
+type Grid = Vec<Vec<Option<Cell>>>;
+
+/// A level being edited: a fixed grid of slots, with undo and redo.
+pub struct Draft {
+    /// Name, par time and pace are kept as loaded. `rows` is always the full
+    /// `ROWS` x `COLS`, so bricks sit where they will in the game.
+    pub level: Level,
+    undo: Vec<Grid>,
+    redo: Vec<Grid>,
+    /// Set when a stroke starts and cleared by its first change, so a whole
+    /// stroke undoes in one step.
+    stroke_started: bool,
+}
+
+impl Default for Draft {
+    fn default() -> Self {
+        Draft::from_level(Level {
+            name: "Custom".to_owned(),
+            par_time: 60.,
+            drop_chance: DEFAULT_DROP_CHANCE,
+            pace: Pace::default(),
+            rows: Vec::new(),
+        })
+    }
+}
+
+impl Draft {
+    /// Takes over `level`, centered in the grid. Whatever doesn't fit is cut.
+    pub fn from_level(mut level: Level) -> Self {
+        let shift = COLS.saturating_sub(level.cols()) / 2;
+        let mut rows = vec![vec![None; COLS]; ROWS];
+        for (r, row) in level.rows.iter().take(ROWS).enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                if let Some(slot) = rows[r].get_mut(c + shift) {
+                    *slot = *cell;
+                }
+            }
+        }
+        level.rows = rows;
+        Draft {
+            level,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            stroke_started: false,
+        }
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> Option<Cell> {
+        self.level.rows.get(row)?.get(col).copied().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.level.rows.iter().flatten().all(Option::is_none)
+    }
+
+    /// Begins a new undo step. Everything painted until the next stroke
+    /// undoes together.
+    pub fn start_stroke(&mut self) {
+        self.stroke_started = true;
+    }
+
+    /// Sets one slot, `None` to erase it. Slots outside the grid are ignored.
+    pub fn paint(&mut self, row: usize, col: usize, cell: Option<Cell>) {
+        if row >= ROWS || col >= COLS || self.level.rows[row][col] == cell {
+            return;
+        }
+        if self.stroke_started {
+            self.stroke_started = false;
+            self.undo.push(self.level.rows.clone());
+            if self.undo.len() > HISTORY {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+        self.level.rows[row][col] = cell;
+    }
+
+    /// Erases every slot, as one undo step.
+    pub fn clear(&mut self) {
+        self.start_stroke();
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                self.paint(row, col, None);
+            }
+        }
+    }
+
+    /// Goes back one stroke. Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(rows) = self.undo.pop() else {
+            return false;
+        };
+        self.redo
+            .push(std::mem::replace(&mut self.level.rows, rows));
+        true
+    }
+
+    /// Replays the last undone stroke. Returns false if there was none.
+    pub fn redo(&mut self) -> bool {
+        let Some(rows) = self.redo.pop() else {
+            return false;
+        };
+        self.undo
+            .push(std::mem::replace(&mut self.level.rows, rows));
+        true
+    }
+
+    /// The level as it would be saved, without the empty rows at the bottom.
+    pub fn to_level(&self) -> Level {
+        let mut level = self.level.clone();
+        while level
+            .rows
+            .last()
+            .is_some_and(|row| row.iter().all(Option::is_none))
+        {
+            level.rows.pop();
+        }
+        level
+    }
+
+    pub fn to_text(&self) -> String {
+        self.to_level().to_text()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BrickKind;
+    use macroquad::prelude::*;
+
+    fn cell(kind: BrickKind, hp: u8, color: Color) -> Option<Cell> {
+        Some(Cell { kind, hp, color })
+    }
+
+    #[test]
+    fn a_stroke_undoes_and_redoes_as_one_step() {
+        let mut draft = Draft::default();
+        let red = cell(BrickKind::Normal, 2, RED);
+        draft.start_stroke();
+        draft.paint(0, 0, red);
+        draft.paint(0, 1, red);
+        draft.start_stroke();
+        draft.paint(3, 3, cell(BrickKind::Steel, 1, GRAY));
+
+        assert!(draft.undo());
+        assert_eq!(draft.cell(3, 3), None);
+        assert_eq!(draft.cell(0, 1), red);
+        assert!(draft.undo());
+        assert!(draft.is_empty());
+        assert!(!draft.undo());
+
+        assert!(draft.redo());
+        assert_eq!(draft.cell(0, 0), red);
+        assert!(draft.redo());
+        assert!(!draft.redo());
+    }
+
+    #[test]
+    fn painting_after_an_undo_drops_the_redo() {
+        let mut draft = Draft::default();
+        draft.start_stroke();
+        draft.paint(1, 1, cell(BrickKind::Normal, 1, BLUE));
+        draft.undo();
+        draft.start_stroke();
+        draft.paint(2, 2, cell(BrickKind::Normal, 1, BLUE));
+        assert!(!draft.redo());
+    }
+
+    #[test]
+    fn saved_text_loads_back_the_same() {
+        let mut draft = Draft::default();
+        draft.level.name = "Round Trip".to_owned();
+        draft.level.pace.speed = 500.;
+        draft.start_stroke();
+        draft.paint(
+            1,
+            0,
+            cell(BrickKind::Normal, 3, Color::from_rgba(237, 135, 150, 192)),
+        );
+        draft.paint(
+            1,
+            9,
+            cell(BrickKind::Explosive, 1, Color::from_hex(0xF5A97F)),
+        );
+        draft.paint(4, 5, cell(BrickKind::Steel, 1, Color::from_hex(0xA5ADCB)));
+
+        let text = draft.to_text();
+        let loaded = Draft::from_level(Level::parse(&text).unwrap());
+        assert_eq!(loaded.level.name, "Round Trip");
+        assert_eq!(loaded.level.pace, draft.level.pace);
+        assert_eq!(loaded.level.rows, draft.level.rows);
+        // Rows below the last brick aren't written
+        assert_eq!(text.lines().skip_while(|l| *l != "[layout]").count(), 6);
+    }
+
+    #[test]
+    fn narrow_levels_load_centered() {
+        let level = Level::simple(2, 6);
+        let draft = Draft::from_level(level);
+        assert_eq!(draft.cell(0, 1), None);
+        assert!(draft.cell(0, 2).is_some());
+        assert!(draft.cell(1, 7).is_some());
+        assert_eq!(draft.cell(1, 8), None);
+    }
+}
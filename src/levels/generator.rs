@@ -0,0 +1,562 @@
+use super::draft::COLS;
+use super::parser::{Cell, Level, Pace};
+use crate::components::BrickKind;
+use crate::components::powerup::DEFAULT_DROP_CHANCE;
+use crate::constants::VELOCITY;
+use crate::rng::Rng;
+use macroquad::prelude::*;
+
+/// Rows a generated level has room for.
+const ROWS: usize = 8;
+/// Noise is drawn on a coarser lattice, this many cells apart, so shapes come
+/// out as blobs rather than speckle.
+const NOISE_STEP: usize = 3;
+/// Normal brick colors by hit points, the toughest last.
+const HP_COLORS: [u32; 5] = [0x8AADF4, 0xA6DA95, 0xEED49F, 0xF5A97F, 0xED8796];
+
+/// Overall shape of a generated layout.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pattern {
+    /// Random shapes, mirrored left to right.
+    Mirrored,
+    /// A triangle, pointing up or down.
+    Pyramid,
+    /// Smooth random blobs, not symmetric.
+    Noise,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 3] = [Pattern::Mirrored, Pattern::Pyramid, Pattern::Noise];
+
+    fn symmetric(self) -> bool {
+        self != Pattern::Noise
+    }
+}
+
+/// How hard a generated level is.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Difficulty {
+    /// Share of the grid filled with bricks, 0 to 1.
+    pub density: f32,
+    /// Hit points to spread over the normal bricks, as the average per brick,
+    /// 1 to 9. Some bricks end up tougher than others.
+    pub hp_budget: f32,
+    /// Share of bricks that are steel, 0 to 1.
+    pub steel_ratio: f32,
+    /// Share of bricks that are explosive, 0 to 1.
+    pub explosive_ratio: f32,
+    pub pace: Pace,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::normal()
+    }
+}
+
+impl Difficulty {
+    pub fn easy() -> Self {
+        Difficulty {
+            density: 0.45,
+            hp_budget: 1.2,
+            steel_ratio: 0.,
+            explosive_ratio: 0.08,
+            pace: Pace {
+                speed: VELOCITY * 0.85,
+                max_speed: VELOCITY * 1.3,
+                ..Pace::default()
+            },
+        }
+    }
+
+    pub fn normal() -> Self {
+        Difficulty {
+            density: 0.6,
+            hp_budget: 1.8,
+            steel_ratio: 0.08,
+            explosive_ratio: 0.05,
+            pace: Pace::default(),
+        }
+    }
+
+    pub fn hard() -> Self {
+        Difficulty {
+            density: 0.75,
+            hp_budget: 3.,
+            steel_ratio: 0.15,
+            explosive_ratio: 0.03,
+            pace: Pace {
+                speed: VELOCITY * 1.15,
+                max_speed: VELOCITY * 1.9,
+                ..Pace::default()
+            },
+        }
+    }
+
+    /// `easy`, `normal` or `hard`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::easy()),
+            "normal" => Some(Difficulty::normal()),
+            "hard" => Some(Difficulty::hard()),
+            _ => None,
+        }
+    }
+}
+
+type Grid = [[Option<Cell>; COLS]; ROWS];
+
+/// A level laid out from `seed` in a pattern the seed picks. The same seed
+/// and difficulty give the same level everywhere: only the seeded generator
+/// and plain arithmetic go into it.
+pub fn generate(seed: u64, difficulty: &Difficulty) -> Level {
+    let mut rng = Rng::new(seed);
+    let pattern = Pattern::ALL[rng.index(Pattern::ALL.len())];
+    generate_pattern(seed, pattern, difficulty)
+}
+
+/// A level in the given pattern, laid out from `seed`.
+pub fn generate_pattern(seed: u64, pattern: Pattern, difficulty: &Difficulty) -> Level {
+    // Its own stream, so picking the pattern in `generate` doesn't shift it
+    let mut rng = Rng::new(seed ^ 0x6C65_7665_6C73);
+    let mirror = pattern.symmetric();
+
+    let scores = match pattern {
+        Pattern::Mirrored => mirrored_scores(&mut rng),
+        Pattern::Pyramid => pyramid_scores(&mut rng),
+        Pattern::Noise => noise_scores(&mut rng),
+    };
+    let mut filled = fill_densest(&scores, difficulty.density.clamp(0., 1.));
+    cut_gaps(&mut filled, mirror, &mut rng);
+
+    let mut grid: Grid = [[None; COLS]; ROWS];
+    for (r, c) in cells(mirror) {
+        if !filled[r][c] {
+            continue;
+        }
+        let roll = rng.next_f32();
+        let kind = if roll < difficulty.steel_ratio {
+            BrickKind::Steel
+        } else if roll < difficulty.steel_ratio + difficulty.explosive_ratio {
+            BrickKind::Explosive
+        } else {
+            BrickKind::Normal
+        };
+        set(&mut grid, r, c, Some(brick(kind, 1)), mirror);
+    }
+
+    open_paths(&mut grid, mirror);
+    if !grid
+        .iter()
+        .flatten()
+        .flatten()
+        .any(|c| c.kind != BrickKind::Steel)
+    {
+        set(
+            &mut grid,
+            ROWS / 2,
+            COLS / 2,
+            Some(brick(BrickKind::Normal, 1)),
+            mirror,
+        );
+    }
+    spread_hp(
+        &mut grid,
+        difficulty.hp_budget.clamp(1., 9.),
+        mirror,
+        &mut rng,
+    );
+
+    let hits: u32 = grid
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|c| c.kind != BrickKind::Steel)
+        .map(|c| c.hp as u32)
+        .sum();
+    Level {
+        name: format!("Seed {seed}"),
+        par_time: (20. + hits as f32 * 1.2).round(),
+        drop_chance: DEFAULT_DROP_CHANCE,
+        pace: difficulty.pace,
+        rows: grid.iter().map(|row| row.to_vec()).collect(),
+    }
+}
+
+/// One mirrored row for endless mode, fuller and tougher the more rows
+/// have come before it. Never steel, so every row can be cleared out of the
+/// way, and never empty.
+pub fn endless_row(rng: &mut Rng, depth: u32) -> Vec<Option<Cell>> {
+    let density = (0.5 + depth as f32 * 0.02).min(0.9);
+    let max_hp = (1 + depth / 8).min(HP_COLORS.len() as u32) as usize;
+    let mut row = vec![None; COLS];
+    for col in 0..COLS.div_ceil(2) {
+        if rng.next_f32() >= density {
+            continue;
+        }
+        let cell = if rng.next_f32() < 0.06 {
+            brick(BrickKind::Explosive, 1)
+        } else {
+            brick(BrickKind::Normal, rng.index(max_hp) as u8 + 1)
+        };
+        row[col] = Some(cell);
+        row[COLS - 1 - col] = Some(cell);
+    }
+    if row.iter().all(Option::is_none) {
+        let col = rng.index(COLS.div_ceil(2));
+        row[col] = Some(brick(BrickKind::Normal, 1));
+        row[COLS - 1 - col] = row[col];
+    }
+    row
+}
+
+/// The cells to decide on: all of them, or only the left half (and middle
+/// column) when the right half mirrors it.
+fn cells(mirror: bool) -> impl Iterator<Item = (usize, usize)> {
+    let cols = if mirror { COLS.div_ceil(2) } else { COLS };
+    (0..ROWS).flat_map(move |r| (0..cols).map(move |c| (r, c)))
+}
+
+fn set(grid: &mut Grid, row: usize, col: usize, cell: Option<Cell>, mirror: bool) {
+    grid[row][col] = cell;
+    if mirror {
+        grid[row][COLS - 1 - col] = cell;
+    }
+}
+
+fn brick(kind: BrickKind, hp: u8) -> Cell {
+    let color = match kind {
+        BrickKind::Normal => {
+            let mut color = Color::from_hex(HP_COLORS[(hp as usize - 1).min(HP_COLORS.len() - 1)]);
+            color.a = 192. / 255.;
+            color
+        }
+        BrickKind::Steel => Color::from_hex(0xA5ADCB),
+        BrickKind::Explosive => Color::from_hex(0xF5A97F),
+    };
+    Cell { kind, hp, color }
+}
+
+/// Random scores for the left half, copied to the right.
+fn mirrored_scores(rng: &mut Rng) -> [[f32; COLS]; ROWS] {
+    let mut scores = [[0.; COLS]; ROWS];
+    for (r, c) in cells(true) {
+        let score = rng.next_f32();
+        scores[r][c] = score;
+        scores[r][COLS - 1 - c] = score;
+    }
+    scores
+}
+
+/// Highest along a triangle's center line and widening towards its base, so
+/// the densest cells make a pyramid. A little mirrored jitter roughens the
+/// edges.
+fn pyramid_scores(rng: &mut Rng) -> [[f32; COLS]; ROWS] {
+    let upright = rng.next_u64() & 1 == 0;
+    let jitter = mirrored_scores(rng);
+    let half = COLS as f32 / 2.;
+    let mut scores = [[0.; COLS]; ROWS];
+    for (r, row) in scores.iter_mut().enumerate() {
+        let depth = if upright { r } else { ROWS - 1 - r } as f32 / (ROWS - 1) as f32;
+        for (c, score) in row.iter_mut().enumerate() {
+            let off_center = (c as f32 + 0.5 - half).abs() / half;
+            *score = depth - off_center + jitter[r][c] * 0.15;
+        }
+    }
+    scores
+}
+
+/// Smoothed value noise: random values on a coarse lattice, blended between
+/// with a smoothstep curve.
+fn noise_scores(rng: &mut Rng) -> [[f32; COLS]; ROWS] {
+    let lattice_rows = ROWS / NOISE_STEP + 2;
+    let lattice_cols = COLS / NOISE_STEP + 2;
+    let lattice: Vec<f32> = (0..lattice_rows * lattice_cols)
+        .map(|_| rng.next_f32())
+        .collect();
+    let at = |r: usize, c: usize| lattice[r * lattice_cols + c];
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+
+    let mut scores = [[0.; COLS]; ROWS];
+    for (r, row) in scores.iter_mut().enumerate() {
+        let (r0, ty) = (
+            r / NOISE_STEP,
+            smooth((r % NOISE_STEP) as f32 / NOISE_STEP as f32),
+        );
+        for (c, score) in row.iter_mut().enumerate() {
+            let (c0, tx) = (
+                c / NOISE_STEP,
+                smooth((c % NOISE_STEP) as f32 / NOISE_STEP as f32),
+            );
+            let top = at(r0, c0) + (at(r0, c0 + 1) - at(r0, c0)) * tx;
+            let bottom = at(r0 + 1, c0) + (at(r0 + 1, c0 + 1) - at(r0 + 1, c0)) * tx;
+            *score = top + (bottom - top) * ty;
+        }
+    }
+    scores
+}
+
+/// Fills the cells scoring highest, about `density` of the grid. Cells tied
+/// with the last one taken are filled too, so mirrored scores stay mirrored.
+fn fill_densest(scores: &[[f32; COLS]; ROWS], density: f32) -> [[bool; COLS]; ROWS] {
+    let mut sorted: Vec<f32> = scores.iter().flatten().copied().collect();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let count = (density * sorted.len() as f32).round() as usize;
+    let mut filled = [[false; COLS]; ROWS];
+    if count == 0 {
+        return filled;
+    }
+    let threshold = sorted[count - 1];
+    for (r, row) in scores.iter().enumerate() {
+        for (c, &score) in row.iter().enumerate() {
+            filled[r][c] = score >= threshold;
+        }
+    }
+    filled
+}
+
+/// Clears up to two whole rows or columns, for lanes through the layout.
+fn cut_gaps(filled: &mut [[bool; COLS]; ROWS], mirror: bool, rng: &mut Rng) {
+    for _ in 0..rng.index(3) {
+        if rng.next_u64() & 1 == 0 {
+            let row = rng.index(ROWS);
+            filled[row] = [false; COLS];
+        } else {
+            let col = rng.index(COLS);
+            for row in filled.iter_mut() {
+                row[col] = false;
+                if mirror {
+                    row[COLS - 1 - col] = false;
+                }
+            }
+        }
+    }
+}
+
+/// Which cells a ball coming up from the paddle can get to, breaking
+/// everything but steel on the way. Going around the sides or over the top
+/// isn't counted, so anything reached here is surely reachable in play.
+fn reachable(grid: &Grid) -> [[bool; COLS]; ROWS] {
+    let open = |r: usize, c: usize| grid[r][c].is_none_or(|cell| cell.kind != BrickKind::Steel);
+    let mut reached = [[false; COLS]; ROWS];
+    let mut pending: Vec<(usize, usize)> = (0..COLS)
+        .filter(|&c| open(ROWS - 1, c))
+        .map(|c| (ROWS - 1, c))
+        .collect();
+    while let Some((r, c)) = pending.pop() {
+        if reached[r][c] {
+            continue;
+        }
+        reached[r][c] = true;
+        let neighbours = [
+            (r.wrapping_sub(1), c),
+            (r + 1, c),
+            (r, c.wrapping_sub(1)),
+            (r, c + 1),
+        ];
+        for (nr, nc) in neighbours {
+            if nr < ROWS && nc < COLS && !reached[nr][nc] && open(nr, nc) {
+                pending.push((nr, nc));
+            }
+        }
+    }
+    reached
+}
+
+/// Turns steel into normal bricks below any breakable brick walled off from
+/// the paddle, until every breakable brick can be reached.
+fn open_paths(grid: &mut Grid, mirror: bool) {
+    loop {
+        let reached = reachable(grid);
+        let shut_in = (0..ROWS)
+            .flat_map(|r| (0..COLS).map(move |c| (r, c)))
+            .find(|&(r, c)| {
+                grid[r][c].is_some_and(|b| b.kind != BrickKind::Steel) && !reached[r][c]
+            });
+        let Some((row, col)) = shut_in else {
+            return;
+        };
+        for r in row + 1..ROWS {
+            if grid[r][col].is_some_and(|b| b.kind == BrickKind::Steel) {
+                let opened = Some(brick(BrickKind::Normal, 1));
+                set(grid, r, col, opened, mirror);
+                // The mirrored cell may be the one on the left
+                set(grid, r, COLS - 1 - col, opened, mirror);
+            }
+        }
+    }
+}
+
+/// Raises normal bricks above one hit point until the grid's average meets
+/// `budget`, picking bricks at random.
+fn spread_hp(grid: &mut Grid, budget: f32, mirror: bool, rng: &mut Rng) {
+    let normals: Vec<(usize, usize)> = cells(mirror)
+        .filter(|&(r, c)| grid[r][c].is_some_and(|b| b.kind == BrickKind::Normal))
+        .collect();
+    if normals.is_empty() {
+        return;
+    }
+    let total = (budget * normals.len() as f32).round() as usize;
+    let extra = total.saturating_sub(normals.len()).min(normals.len() * 8);
+    for _ in 0..extra {
+        // A brick already at 9 passes the point on to the next one with room
+        let start = rng.index(normals.len());
+        let Some((r, c, hp)) = (0..normals.len())
+            .map(|i| normals[(start + i) % normals.len()])
+            .map(|(r, c)| (r, c, grid[r][c].map_or(1, |b| b.hp)))
+            .find(|&(.., hp)| hp < 9)
+        else {
+            return;
+        };
+        set(grid, r, c, Some(brick(BrickKind::Normal, hp + 1)), mirror);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breakable(level: &Level) -> usize {
+        level
+            .rows
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|c| c.kind != BrickKind::Steel)
+            .count()
+    }
+
+    #[test]
+    fn same_seed_same_level() {
+        for seed in 0..20 {
+            let a = generate(seed, &Difficulty::hard());
+            let b = generate(seed, &Difficulty::hard());
+            assert_eq!(a.to_text(), b.to_text());
+        }
+        assert_ne!(
+            generate(1, &Difficulty::normal()).to_text(),
+            generate(2, &Difficulty::normal()).to_text()
+        );
+    }
+
+    #[test]
+    fn symmetric_patterns_mirror() {
+        for seed in 0..20 {
+            for pattern in [Pattern::Mirrored, Pattern::Pyramid] {
+                let level = generate_pattern(seed, pattern, &Difficulty::hard());
+                for row in &level.rows {
+                    let mut flipped = row.clone();
+                    flipped.reverse();
+                    assert_eq!(row, &flipped, "seed {seed} {pattern:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_breakable_brick_is_reachable() {
+        let difficulty = Difficulty {
+            steel_ratio: 0.6,
+            ..Difficulty::hard()
+        };
+        for seed in 0..200 {
+            for pattern in Pattern::ALL {
+                let level = generate_pattern(seed, pattern, &difficulty);
+                let mut grid: Grid = [[None; COLS]; ROWS];
+                for (r, row) in level.rows.iter().enumerate() {
+                    grid[r].copy_from_slice(row);
+                }
+                let reached = reachable(&grid);
+                for (r, row) in grid.iter().enumerate() {
+                    for (c, cell) in row.iter().enumerate() {
+                        if cell.is_some_and(|b| b.kind != BrickKind::Steel) {
+                            assert!(reached[r][c], "seed {seed} {pattern:?} ({r}, {c})");
+                        }
+                    }
+                }
+                assert!(breakable(&level) > 0);
+                Level::parse(&level.to_text()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn difficulty_shapes_the_level() {
+        let count = |difficulty: &Difficulty, kind| -> usize {
+            (0..50)
+                .map(|seed| generate(seed, difficulty))
+                .map(|l| {
+                    l.rows
+                        .iter()
+                        .flatten()
+                        .flatten()
+                        .filter(|c| c.kind == kind)
+                        .count()
+                })
+                .sum()
+        };
+        let easy = Difficulty::easy();
+        let hard = Difficulty::hard();
+        assert_eq!(count(&easy, BrickKind::Steel), 0);
+        assert!(count(&hard, BrickKind::Steel) > 0);
+        assert!(count(&hard, BrickKind::Normal) > count(&easy, BrickKind::Normal));
+
+        let level = generate(
+            3,
+            &Difficulty {
+                hp_budget: 9.,
+                ..hard
+            },
+        );
+        assert!(
+            level
+                .rows
+                .iter()
+                .flatten()
+                .flatten()
+                .all(|c| c.kind != BrickKind::Normal || c.hp == 9)
+        );
+    }
+
+    #[test]
+    fn endless_rows_get_tougher() {
+        let mut rng = Rng::new(9);
+        let hits = |rng: &mut Rng, depth| -> u32 {
+            (0..50)
+                .flat_map(|_| endless_row(rng, depth))
+                .flatten()
+                .map(|c| {
+                    assert_ne!(c.kind, BrickKind::Steel);
+                    c.hp as u32
+                })
+                .sum()
+        };
+        assert!(hits(&mut rng, 40) > hits(&mut rng, 0) * 2);
+        for depth in 0..100 {
+            let row = endless_row(&mut rng, depth);
+            assert!(row.iter().any(Option::is_some));
+            assert!(row.iter().eq(row.iter().rev()));
+        }
+    }
+
+    /// Pins one layout down exactly, so a change in how levels come out, on
+    /// any platform, shows up here.
+    #[test]
+    fn layout_is_stable() {
+        let level = generate_pattern(2024, Pattern::Noise, &Difficulty::normal());
+        let layout = level.to_text();
+        let grid = layout.split("[layout]\n").nth(1).unwrap();
+        assert_eq!(grid, GOLDEN);
+    }
+
+    const GOLDEN: &str = "\
+ABBBCDAEAB
+EACBEE..AE
+EEBBE.....
+ECBED.....
+DDBEF.....
+..EAE...CC
+...BB...FB
+..EAEE..DB
+";
+}
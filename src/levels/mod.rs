@@ -0,0 +1,77 @@
+pub mod draft;
+pub mod generator;
+pub mod parser;
+
+pub use parser::{Level, Pace};
+
+use crate::components::powerup::DEFAULT_DROP_CHANCE;
+
+/// Stages played in order; clearing the last one wins the game.
+pub const CAMPAIGN: [&str; 3] = [
+    include_str!("../../assets/levels/01_warm_up.lvl"),
+    include_str!("../../assets/levels/02_checkers.lvl"),
+    include_str!("../../assets/levels/03_rainbow.lvl"),
+];
+
+/// The stages a run plays through, in order.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Course {
+    /// The built-in stages, starting from stage `first`.
+    Campaign { first: usize },
+    /// One level, kept as the text of its file; clearing it wins.
+    Custom(String),
+    /// Rows of bricks keep coming until they reach the paddle.
+    Endless,
+}
+
+impl Default for Course {
+    fn default() -> Self {
+        Course::Campaign { first: 0 }
+    }
+}
+
+impl Course {
+    /// Index of the stage a run starts on.
+    pub fn first(&self) -> usize {
+        match self {
+            Course::Campaign { first } => *first,
+            Course::Custom(_) | Course::Endless => 0,
+        }
+    }
+
+    pub fn stage_count(&self) -> usize {
+        match self {
+            Course::Campaign { .. } => CAMPAIGN.len(),
+            Course::Custom(_) | Course::Endless => 1,
+        }
+    }
+
+    /// Parses stage `index`, falling back to a plain grid if the file is
+    /// broken. An endless run starts from an empty field; the world brings
+    /// its rows in.
+    pub fn stage(&self, index: usize) -> Level {
+        let src = match self {
+            Course::Campaign { .. } => CAMPAIGN[index],
+            Course::Custom(src) => src,
+            Course::Endless => {
+                return Level {
+                    name: "Endless".to_owned(),
+                    par_time: 0.,
+                    drop_chance: DEFAULT_DROP_CHANCE,
+                    pace: Pace::default(),
+                    rows: Vec::new(),
+                };
+            }
+        };
+        match Level::parse(src) {
+            Ok(level) => {
+                log::info!("Loaded level \"{}\" (par {}s)", level.name, level.par_time);
+                level
+            }
+            Err(e) => {
+                log::error!("Failed to parse level {}: {e}", index + 1);
+                Level::simple(4, 10)
+            }
+        }
+    }
+}
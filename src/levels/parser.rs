@@ -0,0 +1,742 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use macroquad::prelude::*;
+
+use crate::components::bricks::{Brick, BrickKind};
+use crate::components::powerup::DEFAULT_DROP_CHANCE;
+use crate::constants::VELOCITY;
+
+const EMPTY_CELLS: [char; 2] = ['.', ' '];
+
+/// A brick type declared in the `[legend]` section.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub kind: BrickKind,
+    pub hp: u8,
+    pub color: Color,
+}
+
+/// How fast the ball flies and how that changes over a stage.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pace {
+    /// Speed a stage starts at, in arena units per second.
+    pub speed: f32,
+    /// Speed the ball never goes past, however long the rally.
+    pub max_speed: f32,
+    /// Speed gained each second a ball is in flight.
+    pub speed_up: f32,
+    /// Speed gained each time a ball hits a brick.
+    pub brick_speed_up: f32,
+    /// Speed gained for each row a ball climbs past the highest it reached.
+    pub row_speed_up: f32,
+    /// Shallowest a ball may travel, in degrees from horizontal.
+    pub min_angle: f32,
+}
+
+impl Default for Pace {
+    fn default() -> Self {
+        Pace {
+            speed: VELOCITY,
+            max_speed: VELOCITY * 1.6,
+            speed_up: 3.,
+            brick_speed_up: 1.,
+            row_speed_up: 15.,
+            min_angle: 15.,
+        }
+    }
+}
+
+impl Pace {
+    /// Smallest share of its speed a ball must move up or down with.
+    pub fn min_rise(&self) -> f32 {
+        // Worked out in f64 and rounded, so it comes out the same everywhere
+        (self.min_angle as f64).to_radians().sin() as f32
+    }
+}
+
+#[derive(Clone)]
+pub struct Level {
+    pub name: String,
+    /// Time in seconds a good run should clear the level in.
+    pub par_time: f32,
+    /// Chance, 0 to 1, that a destroyed brick drops a power-up.
+    pub drop_chance: f32,
+    pub pace: Pace,
+    /// Layout rows, top to bottom. Rows may be shorter than `cols()`.
+    pub rows: Vec<Vec<Option<Cell>>>,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ParseErrorKind {
+    MissingSection(&'static str),
+    MissingKey(&'static str),
+    UnknownSection(String),
+    DuplicateSection(String),
+    ExpectedKeyValue,
+    UnknownKey(String),
+    InvalidPar(String),
+    InvalidDropChance(String),
+    InvalidSpeed(String, String),
+    InvalidSpeedUp(String, String),
+    InvalidAngle(String),
+    MaxSpeedBelowSpeed,
+    InvalidLegendChar(String),
+    DuplicateLegend(char),
+    UnknownBrickKind(String),
+    InvalidHp(String),
+    UnbreakableHp,
+    InvalidColor(String),
+    MissingColor,
+    UnexpectedToken(String),
+    UnknownCell(char),
+    EmptyLayout,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::MissingSection(s) => write!(f, "missing [{s}] section"),
+            ParseErrorKind::MissingKey(k) => write!(f, "missing `{k}` in [level]"),
+            ParseErrorKind::UnknownSection(s) => write!(f, "unknown section [{s}]"),
+            ParseErrorKind::DuplicateSection(s) => write!(f, "section [{s}] appears twice"),
+            ParseErrorKind::ExpectedKeyValue => write!(f, "expected `key = value`"),
+            ParseErrorKind::UnknownKey(k) => write!(f, "unknown key `{k}`"),
+            ParseErrorKind::InvalidPar(v) => write!(f, "invalid par time `{v}`"),
+            ParseErrorKind::InvalidDropChance(v) => {
+                write!(f, "invalid drop chance `{v}`, expected 0 to 1")
+            }
+            ParseErrorKind::InvalidSpeed(k, v) => {
+                write!(f, "invalid {k} `{v}`, expected a speed above 0")
+            }
+            ParseErrorKind::InvalidSpeedUp(k, v) => {
+                write!(f, "invalid {k} `{v}`, expected 0 or more")
+            }
+            ParseErrorKind::InvalidAngle(v) => {
+                write!(f, "invalid min_angle `{v}`, expected 0 to 60 degrees")
+            }
+            ParseErrorKind::MaxSpeedBelowSpeed => write!(f, "max_speed is below speed"),
+            ParseErrorKind::InvalidLegendChar(s) => {
+                write!(
+                    f,
+                    "legend key `{s}` must be a single character other than '.' or ' '"
+                )
+            }
+            ParseErrorKind::DuplicateLegend(c) => write!(f, "legend entry '{c}' defined twice"),
+            ParseErrorKind::UnknownBrickKind(k) => {
+                write!(
+                    f,
+                    "unknown brick kind `{k}`, expected normal, steel or explosive"
+                )
+            }
+            ParseErrorKind::InvalidHp(v) => write!(f, "invalid hit points `{v}`, expected 1-9"),
+            ParseErrorKind::UnbreakableHp => write!(f, "steel bricks can't have hit points"),
+            ParseErrorKind::InvalidColor(c) => {
+                write!(f, "invalid color `{c}`, expected #RRGGBB or #RRGGBBAA")
+            }
+            ParseErrorKind::MissingColor => write!(f, "missing color, expected #RRGGBB"),
+            ParseErrorKind::UnexpectedToken(t) => write!(f, "unexpected `{t}` after the color"),
+            ParseErrorKind::UnknownCell(c) => write!(f, "'{c}' is not in the legend"),
+            ParseErrorKind::EmptyLayout => write!(f, "layout has no bricks"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(PartialEq)]
+enum Section {
+    None,
+    Level,
+    Legend,
+    Layout,
+}
+
+impl Level {
+    /// Parses a level file. See `assets/levels/01_warm_up.lvl` for the format.
+    pub fn parse(src: &str) -> Result<Level, ParseError> {
+        let mut name = None;
+        let mut par_time = None;
+        let mut drop_chance = DEFAULT_DROP_CHANCE;
+        let mut pace = Pace::default();
+        let mut legend: HashMap<char, Cell> = HashMap::new();
+        let mut layout: Vec<(usize, &str)> = Vec::new();
+
+        let mut section = Section::None;
+        let mut seen: Vec<&str> = Vec::new();
+        let mut last_line = 0;
+
+        for (i, raw) in src.lines().enumerate() {
+            let line_no = i + 1;
+            last_line = line_no;
+            let line = raw.trim_end_matches('\r');
+
+            // Everything after [layout] is grid, blank lines included
+            if section == Section::Layout {
+                layout.push((line_no, line));
+                continue;
+            }
+
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with(';') {
+                continue;
+            }
+            let indent = char_len(line) - char_len(line.trim_start());
+
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                let err = |kind| ParseError {
+                    line: line_no,
+                    column: indent + 1,
+                    kind,
+                };
+                if seen.contains(&header) {
+                    return Err(err(ParseErrorKind::DuplicateSection(header.to_owned())));
+                }
+                section = match header {
+                    "level" => Section::Level,
+                    "legend" => Section::Legend,
+                    "layout" => Section::Layout,
+                    _ => return Err(err(ParseErrorKind::UnknownSection(header.to_owned()))),
+                };
+                seen.push(header);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(ParseError {
+                    line: line_no,
+                    column: indent + 1,
+                    kind: ParseErrorKind::ExpectedKeyValue,
+                });
+            };
+            let value_col = char_len(key) + 2 + (char_len(value) - char_len(value.trim_start()));
+            let key_col = indent + 1;
+            let key = key.trim();
+            let value = value.trim();
+
+            match section {
+                Section::Level => match key {
+                    "name" => name = Some(value.to_owned()),
+                    "par" => match value.parse::<f32>() {
+                        Ok(par) if par > 0.0 => par_time = Some(par),
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: value_col,
+                                kind: ParseErrorKind::InvalidPar(value.to_owned()),
+                            });
+                        }
+                    },
+                    "drops" => match value.parse::<f32>() {
+                        Ok(chance) if (0.0..=1.0).contains(&chance) => drop_chance = chance,
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: value_col,
+                                kind: ParseErrorKind::InvalidDropChance(value.to_owned()),
+                            });
+                        }
+                    },
+                    "speed" | "max_speed" => match value.parse::<f32>() {
+                        Ok(speed) if speed.is_finite() && speed > 0. => match key {
+                            "speed" => pace.speed = speed,
+                            _ => pace.max_speed = speed,
+                        },
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: value_col,
+                                kind: ParseErrorKind::InvalidSpeed(
+                                    key.to_owned(),
+                                    value.to_owned(),
+                                ),
+                            });
+                        }
+                    },
+                    "speed_up" | "brick_speed_up" | "row_speed_up" => match value.parse::<f32>() {
+                        Ok(gain) if gain.is_finite() && gain >= 0. => match key {
+                            "speed_up" => pace.speed_up = gain,
+                            "brick_speed_up" => pace.brick_speed_up = gain,
+                            _ => pace.row_speed_up = gain,
+                        },
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: value_col,
+                                kind: ParseErrorKind::InvalidSpeedUp(
+                                    key.to_owned(),
+                                    value.to_owned(),
+                                ),
+                            });
+                        }
+                    },
+                    "min_angle" => match value.parse::<f32>() {
+                        Ok(angle) if (0.0..=60.0).contains(&angle) => pace.min_angle = angle,
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: value_col,
+                                kind: ParseErrorKind::InvalidAngle(value.to_owned()),
+                            });
+                        }
+                    },
+                    _ => {
+                        return Err(ParseError {
+                            line: line_no,
+                            column: key_col,
+                            kind: ParseErrorKind::UnknownKey(key.to_owned()),
+                        });
+                    }
+                },
+                Section::Legend => {
+                    let mut chars = key.chars();
+                    let symbol = match (chars.next(), chars.next()) {
+                        (Some(c), None) if !EMPTY_CELLS.contains(&c) => c,
+                        _ => {
+                            return Err(ParseError {
+                                line: line_no,
+                                column: key_col,
+                                kind: ParseErrorKind::InvalidLegendChar(key.to_owned()),
+                            });
+                        }
+                    };
+                    if legend.contains_key(&symbol) {
+                        return Err(ParseError {
+                            line: line_no,
+                            column: key_col,
+                            kind: ParseErrorKind::DuplicateLegend(symbol),
+                        });
+                    }
+                    let cell = parse_cell(value).map_err(|(offset, kind)| ParseError {
+                        line: line_no,
+                        column: value_col + offset,
+                        kind,
+                    })?;
+                    legend.insert(symbol, cell);
+                }
+                Section::None | Section::Layout => {
+                    return Err(ParseError {
+                        line: line_no,
+                        column: key_col,
+                        kind: ParseErrorKind::UnknownKey(key.to_owned()),
+                    });
+                }
+            }
+        }
+
+        let missing = |kind| ParseError {
+            line: last_line + 1,
+            column: 1,
+            kind,
+        };
+        for section in ["level", "legend", "layout"] {
+            if !seen.contains(&section) {
+                return Err(missing(ParseErrorKind::MissingSection(section)));
+            }
+        }
+        let name = name.ok_or_else(|| missing(ParseErrorKind::MissingKey("name")))?;
+        let par_time = par_time.ok_or_else(|| missing(ParseErrorKind::MissingKey("par")))?;
+        if pace.max_speed < pace.speed {
+            return Err(missing(ParseErrorKind::MaxSpeedBelowSpeed));
+        }
+
+        // Trailing blank lines are not part of the grid
+        while layout.last().is_some_and(|(_, l)| l.trim().is_empty()) {
+            layout.pop();
+        }
+
+        let mut rows = Vec::with_capacity(layout.len());
+        for (line_no, line) in layout {
+            let mut row = Vec::new();
+            for (col, c) in line.chars().enumerate() {
+                if EMPTY_CELLS.contains(&c) {
+                    row.push(None);
+                    continue;
+                }
+                match legend.get(&c) {
+                    Some(cell) => row.push(Some(*cell)),
+                    None => {
+                        return Err(ParseError {
+                            line: line_no,
+                            column: col + 1,
+                            kind: ParseErrorKind::UnknownCell(c),
+                        });
+                    }
+                }
+            }
+            rows.push(row);
+        }
+
+        if !rows.iter().flatten().any(|c| c.is_some()) {
+            return Err(missing(ParseErrorKind::EmptyLayout));
+        }
+
+        Ok(Level {
+            name,
+            par_time,
+            drop_chance,
+            pace,
+            rows,
+        })
+    }
+
+    /// A full `rows` x `cols` rectangle of plain bricks.
+    pub fn simple(rows: usize, cols: usize) -> Level {
+        let mut color = WHITE;
+        color.a = 0.5;
+        let cell = Cell {
+            kind: BrickKind::Normal,
+            hp: 1,
+            color,
+        };
+        Level {
+            name: "Simple".to_owned(),
+            par_time: 60.,
+            drop_chance: DEFAULT_DROP_CHANCE,
+            pace: Pace::default(),
+            rows: vec![vec![Some(cell); cols]; rows],
+        }
+    }
+
+    /// Writes the level in the level file format. Each distinct brick type
+    /// gets a legend symbol in the order it first appears.
+    pub fn to_text(&self) -> String {
+        let mut legend: Vec<Cell> = Vec::new();
+        for cell in self.rows.iter().flatten().flatten() {
+            if !legend.contains(cell) {
+                legend.push(*cell);
+            }
+        }
+
+        let mut out = format!("[level]\nname = {}\npar = {}\n", self.name, self.par_time);
+        if self.drop_chance != DEFAULT_DROP_CHANCE {
+            out.push_str(&format!("drops = {}\n", self.drop_chance));
+        }
+        let pace = self.pace;
+        let default = Pace::default();
+        for (key, value, standard) in [
+            ("speed", pace.speed, default.speed),
+            ("max_speed", pace.max_speed, default.max_speed),
+            ("speed_up", pace.speed_up, default.speed_up),
+            (
+                "brick_speed_up",
+                pace.brick_speed_up,
+                default.brick_speed_up,
+            ),
+            ("row_speed_up", pace.row_speed_up, default.row_speed_up),
+            ("min_angle", pace.min_angle, default.min_angle),
+        ] {
+            if value != standard {
+                out.push_str(&format!("{key} = {value}\n"));
+            }
+        }
+
+        out.push_str("\n[legend]\n");
+        for (i, cell) in legend.iter().enumerate() {
+            let kind = match cell.kind {
+                BrickKind::Normal => "normal",
+                BrickKind::Steel => "steel",
+                BrickKind::Explosive => "explosive",
+            };
+            let hp = if cell.hp > 1 {
+                format!(":{}", cell.hp)
+            } else {
+                String::new()
+            };
+            out.push_str(&format!(
+                "{} = {kind}{hp} {}\n",
+                legend_symbol(i),
+                color_hex(cell.color)
+            ));
+        }
+
+        out.push_str("\n[layout]\n");
+        for row in &self.rows {
+            let line: String = row
+                .iter()
+                .map(|cell| match cell {
+                    Some(cell) => legend_symbol(legend.iter().position(|c| c == cell).unwrap()),
+                    None => '.',
+                })
+                .collect();
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn cols(&self) -> usize {
+        self.rows.iter().map(|r| r.len()).max().unwrap_or(0)
+    }
+
+    /// Lays the grid out centered horizontally in an arena `arena_width` wide.
+    pub fn bricks(&self, arena_width: f32) -> Vec<Brick> {
+        let cols = self.cols();
+        let mut bricks = Vec::new();
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, cell) in row.iter().enumerate() {
+                if let Some(cell) = cell {
+                    let pos = Brick::grid_pos(r, c, cols, arena_width);
+                    bricks.push(Brick {
+                        row: r,
+                        ..Brick::new(pos, cell.kind, cell.hp, cell.color)
+                    });
+                }
+            }
+        }
+        bricks
+    }
+}
+
+/// Parses `<kind>[:hp] <color>`. Errors carry the char offset of the bad token.
+fn parse_cell(value: &str) -> Result<Cell, (usize, ParseErrorKind)> {
+    let mut tokens = words(value).into_iter();
+    let (kind_offset, kind_token) = tokens.next().unwrap_or((0, ""));
+    let (kind_name, hp_token) = match kind_token.split_once(':') {
+        Some((name, hp)) => (name, Some(hp)),
+        None => (kind_token, None),
+    };
+    let kind = match kind_name {
+        "normal" => BrickKind::Normal,
+        "steel" => BrickKind::Steel,
+        "explosive" => BrickKind::Explosive,
+        _ => {
+            return Err((
+                kind_offset,
+                ParseErrorKind::UnknownBrickKind(kind_name.to_owned()),
+            ));
+        }
+    };
+    let hp_offset = kind_offset + char_len(kind_name) + 1;
+    let hp = match hp_token {
+        None => 1,
+        // Steel can't be broken, so hit points make no sense for it
+        Some(hp) if kind != BrickKind::Steel => match hp.parse::<u8>() {
+            Ok(hp @ 1..=9) => hp,
+            _ => return Err((hp_offset, ParseErrorKind::InvalidHp(hp.to_owned()))),
+        },
+        Some(_) => return Err((hp_offset, ParseErrorKind::UnbreakableHp)),
+    };
+
+    let Some((color_offset, color_token)) = tokens.next() else {
+        return Err((char_len(value), ParseErrorKind::MissingColor));
+    };
+    let color = parse_color(color_token).ok_or_else(|| {
+        (
+            color_offset,
+            ParseErrorKind::InvalidColor(color_token.to_owned()),
+        )
+    })?;
+    if let Some((offset, extra)) = tokens.next() {
+        return Err((offset, ParseErrorKind::UnexpectedToken(extra.to_owned())));
+    }
+
+    Ok(Cell { kind, hp, color })
+}
+
+/// The whitespace-separated words of `s`, each with its char offset.
+fn words(s: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let ends = s.char_indices().chain([(s.len(), ' ')]).enumerate();
+    for (col, (i, c)) in ends {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((col, i)),
+            (true, Some((from_col, from))) => {
+                words.push((from_col, &s[from..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Length of `s` in chars, which is what error columns count.
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Legend symbol for the `i`th brick type: letters, then digits, then
+/// whatever else reads well.
+fn legend_symbol(i: usize) -> char {
+    const SYMBOLS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    SYMBOLS.chars().nth(i).unwrap_or_else(|| {
+        let extra = (i - SYMBOLS.len()) as u32;
+        char::from_u32(0xC0 + extra).unwrap_or('?')
+    })
+}
+
+/// `#RRGGBB`, or `#RRGGBBAA` when not fully opaque.
+fn color_hex(color: Color) -> String {
+    let [r, g, b, a] = [color.r, color.g, color.b, color.a].map(|c| (c * 255.).round() as u8);
+    if a == 255 {
+        format!("#{r:02X}{g:02X}{b:02X}")
+    } else {
+        format!("#{r:02X}{g:02X}{b:02X}{a:02X}")
+    }
+}
+
+fn parse_color(s: &str) -> Option<Color> {
+    let hex = s.strip_prefix('#')?;
+    if !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        6 => Some(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, 255)),
+        8 => Some(Color::from_rgba(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "\
+[level]
+name = Test
+par = 30
+
+[legend]
+A = normal:2 #FF0000
+S = steel #808080
+
+[layout]
+A.S
+";
+
+    /// Parses `VALID` with `from` replaced by `to`, expecting an error.
+    fn error(from: &str, to: &str) -> (usize, usize, ParseErrorKind) {
+        assert!(VALID.contains(from), "`{from}` is not in the test level");
+        let err = Level::parse(&VALID.replacen(from, to, 1))
+            .err()
+            .expect("the level should not load");
+        (err.line, err.column, err.kind)
+    }
+
+    #[test]
+    fn a_valid_level_loads() {
+        let level = Level::parse(VALID).unwrap();
+        assert_eq!(level.name, "Test");
+        assert_eq!(level.par_time, 30.);
+        assert_eq!(level.rows.len(), 1);
+        let a = level.rows[0][0].unwrap();
+        assert_eq!((a.kind, a.hp), (BrickKind::Normal, 2));
+        assert!(level.rows[0][1].is_none());
+        assert_eq!(level.rows[0][2].unwrap().kind, BrickKind::Steel);
+    }
+
+    #[test]
+    fn legend_errors_point_at_the_bad_token() {
+        use ParseErrorKind::*;
+        assert_eq!(
+            error("A = normal:2", "A = brick:2"),
+            (6, 5, UnknownBrickKind("brick".to_owned()))
+        );
+        assert_eq!(
+            error("normal:2", "normal:10"),
+            (6, 12, InvalidHp("10".to_owned()))
+        );
+        assert_eq!(error("steel", "steel:3"), (7, 11, UnbreakableHp));
+        assert_eq!(
+            error("#FF0000", "#FF00"),
+            (6, 14, InvalidColor("#FF00".to_owned()))
+        );
+        assert_eq!(error(" #FF0000", ""), (6, 13, MissingColor));
+        assert_eq!(
+            error("#FF0000", "#FF0000 shiny"),
+            (6, 22, UnexpectedToken("shiny".to_owned()))
+        );
+        // The color is found by position, not by searching for its text
+        assert_eq!(
+            error("A = normal:2 #FF0000", "A = explosive e"),
+            (6, 15, InvalidColor("e".to_owned()))
+        );
+        assert_eq!(
+            error("S = steel", "SS = steel"),
+            (7, 1, InvalidLegendChar("SS".to_owned()))
+        );
+        assert_eq!(
+            error("S = steel", "A = steel"),
+            (7, 1, DuplicateLegend('A'))
+        );
+    }
+
+    #[test]
+    fn columns_count_chars_not_bytes() {
+        use ParseErrorKind::*;
+        // `É`, `é` and the ideographic space are more than one byte each
+        assert_eq!(
+            error("A = normal:2", "É = normal:x"),
+            (6, 12, InvalidHp("x".to_owned()))
+        );
+        assert_eq!(
+            error("A = normal:2 #FF0000", "É = normal:2 #FF0000 é"),
+            (6, 22, UnexpectedToken("é".to_owned()))
+        );
+        assert_eq!(
+            error("S = steel", "\u{3000}SS = steel"),
+            (7, 2, InvalidLegendChar("SS".to_owned()))
+        );
+    }
+
+    #[test]
+    fn layout_errors_point_at_the_cell() {
+        assert_eq!(
+            error("A.S\n", "A.S\n..X\n"),
+            (11, 3, ParseErrorKind::UnknownCell('X'))
+        );
+        assert_eq!(
+            error("A.S\n", "...\n"),
+            (11, 1, ParseErrorKind::EmptyLayout)
+        );
+    }
+
+    #[test]
+    fn missing_parts_are_reported_after_the_last_line() {
+        use ParseErrorKind::*;
+        assert_eq!(error("name = Test\n", ""), (10, 1, MissingKey("name")));
+        assert_eq!(error("par = 30\n", ""), (10, 1, MissingKey("par")));
+        assert_eq!(
+            error("[legend]\nA = normal:2 #FF0000\nS = steel #808080\n", ""),
+            (8, 1, MissingSection("legend"))
+        );
+        assert_eq!(
+            error("[layout]\nA.S\n", ""),
+            (9, 1, MissingSection("layout"))
+        );
+    }
+
+    #[test]
+    fn level_keys_are_checked() {
+        use ParseErrorKind::*;
+        assert_eq!(
+            error("par = 30", "par = soon"),
+            (3, 7, InvalidPar("soon".to_owned()))
+        );
+        assert_eq!(
+            error("par = 30", "colour = red"),
+            (3, 1, UnknownKey("colour".to_owned()))
+        );
+        assert_eq!(
+            error("[layout]", "[grid]"),
+            (9, 1, UnknownSection("grid".to_owned()))
+        );
+        assert_eq!(error("par = 30", "par"), (3, 1, ExpectedKeyValue));
+    }
+}
@@ -0,0 +1,14 @@
+//! Game simulation and drawing for rayball. The window and the main loop
+//! live in the binary; everything here runs headless as well.
+
+#[doc(hidden)]
+pub mod bench;
+pub mod components;
+pub mod constants;
+pub mod input;
+pub mod levels;
+pub mod replay;
+pub mod rng;
+pub mod settings;
+pub mod systems;
+pub mod world;
@@ -1,85 +1,136 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod components;
-mod constants;
+mod app;
+mod cli;
 mod game;
-mod systems;
+mod headless;
+mod playback;
+mod scenes;
 
-use std::sync::OnceLock;
+use std::process::ExitCode;
 
-use constants::*;
-use game::Game;
+use app::App;
+use cli::Options;
+use game::Setup;
 use macroquad::prelude::*;
-use systems::audio::SoundManager;
+use rayball::replay::Replay;
+use rayball::settings::{Display, Settings};
+use rayball::systems::audio::SoundManager;
+use rayball::systems::render::{self, DEFAULT_FONT};
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("rayball: {e}\n\n{}", cli::USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+    init_logger(&options);
+
+    let (setup, replay) = match options.setup().and_then(|s| Ok((s, options.replay()?))) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            log::error!("{e}");
+            eprintln!("rayball: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let settings = Settings::load();
+    let display = options.display(&settings.display);
 
-static DEFAULT_FONT: OnceLock<Font> = OnceLock::new();
+    if options.headless {
+        return headless::run(&setup, replay, options.ticks);
+    }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    init_logger();
+    let conf = window_conf(&display);
+    macroquad::Window::from_config(conf, run(options, settings, setup, replay));
+    ExitCode::SUCCESS
+}
 
+async fn run(options: Options, settings: Settings, setup: Setup, replay: Option<Replay>) {
     let font = load_ttf_font_from_bytes(include_bytes!("../assets/Cousine-Regular.ttf"));
     DEFAULT_FONT.set(font.unwrap()).unwrap();
 
-    let sounds = SoundManager::new();
-    let mut game = Game::new(sounds.await);
+    let sounds = SoundManager::new().await;
+    let standard = setup.is_standard();
+    let mut app = App::new(sounds, settings, setup);
+    if options.mute {
+        app.mute();
+    }
+    if let Some(replay) = replay {
+        app.watch(replay);
+    } else if !standard {
+        // Asked for a particular run, so skip the title screen
+        app.play();
+    }
     log::info!("Game started successfully");
 
     loop {
         let start_frame = get_time();
 
-        game.update();
-        clear_background(BG_COLOR);
-        game.draw();
+        let running = app.update();
+        render::begin_frame();
+        app.draw();
 
-        if is_key_down(KeyCode::Escape) || is_quit_requested() {
+        if !running || is_quit_requested() {
+            app.shutdown();
             break;
         }
         next_frame().await;
-        limit_framerate(start_frame);
+        limit_framerate(start_frame, options.fps_cap.unwrap_or(app.fps_cap()));
     }
 }
 
-fn window_conf() -> Conf {
+fn window_conf(display: &Display) -> Conf {
     Conf {
         window_title: "rayball".to_owned(),
-        window_width: 800,
-        window_height: 600,
+        window_width: display.width as i32,
+        window_height: display.height as i32,
         high_dpi: true,
-        fullscreen: false,
-        window_resizable: false,
+        fullscreen: display.fullscreen,
+        window_resizable: true,
         ..Default::default()
     }
 }
 
-const FRAME_TIME_TARGET: f64 = 1.0 / 120.; // FPS
-
-fn limit_framerate(start_frame: f64) {
+fn limit_framerate(start_frame: f64, fps_cap: u32) {
     #[cfg(not(target_arch = "wasm32"))]
     {
+        if fps_cap == 0 {
+            return;
+        }
+        let frame_time_target = 1. / fps_cap as f64;
         let elapsed = get_time() - start_frame;
-        if elapsed < FRAME_TIME_TARGET {
-            let sleep_time = (FRAME_TIME_TARGET - elapsed) * 1000.0;
+        if elapsed < frame_time_target {
+            let sleep_time = (frame_time_target - elapsed) * 1000.0;
             std::thread::sleep(std::time::Duration::from_millis(sleep_time as u64));
         }
     }
 }
 
-fn init_logger() {
+fn init_logger(options: &Options) {
     #[cfg(not(target_arch = "wasm32"))]
     {
         use simplelog::*;
+        let level = options.log_level();
         let mut loggers: Vec<Box<dyn SharedLogger>> = vec![];
 
         loggers.push(TermLogger::new(
-            LevelFilter::Info,
+            level,
             Config::default(),
             TerminalMode::Mixed,
             ColorChoice::Auto,
         ));
 
-        if let Ok(file) = std::fs::File::create("rayball.log") {
-            loggers.push(WriteLogger::new(LevelFilter::Info, Config::default(), file));
+        let path = options.log_file();
+        match std::fs::File::create(&path) {
+            Ok(file) => loggers.push(WriteLogger::new(level, Config::default(), file)),
+            Err(e) => eprintln!("rayball: could not open log file {}: {e}", path.display()),
         }
 
         CombinedLogger::init(loggers).unwrap();
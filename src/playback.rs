@@ -0,0 +1,66 @@
+use macroquad::prelude::*;
+use rayball::constants::TICK_DT;
+use rayball::input::{Action, Bindings, TickInput};
+use rayball::replay::Replay;
+
+/// Fast-forward rates, cycled through with the replay speed key.
+const SPEEDS: [u32; 4] = [1, 2, 4, 8];
+
+/// Feeds a recorded session back into the world instead of the player's
+/// input, with pause, fast-forward and single-tick stepping.
+pub struct Playback {
+    pub replay: Replay,
+    /// Index of the next tick to play.
+    pub tick: usize,
+    pub paused: bool,
+    speed: usize,
+    step: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay,
+            tick: 0,
+            paused: false,
+            speed: 0,
+            step: false,
+        }
+    }
+
+    pub fn speed(&self) -> u32 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn handle_keys(&mut self, bindings: &Bindings) {
+        if bindings.pressed(Action::ReplayPause) {
+            self.paused = !self.paused;
+        }
+        if bindings.pressed(Action::ReplaySpeed) {
+            self.speed = (self.speed + 1) % SPEEDS.len();
+        }
+        if bindings.pressed(Action::ReplayStep) {
+            // Stepping only makes sense on a still picture
+            self.paused = true;
+            self.step = true;
+        }
+    }
+
+    /// Inputs for the ticks this frame covers at the current speed.
+    pub fn advance(&mut self, frame_time: f32, accumulator: &mut f32) -> Vec<TickInput> {
+        let ticks = if self.paused {
+            *accumulator = 0.;
+            usize::from(std::mem::take(&mut self.step))
+        } else {
+            *accumulator += frame_time * self.speed() as f32;
+            let ticks = (*accumulator / TICK_DT) as usize;
+            *accumulator -= ticks as f32 * TICK_DT;
+            ticks
+        };
+
+        let end = (self.tick + ticks).min(self.replay.inputs.len());
+        let inputs = self.replay.inputs[self.tick..end].to_vec();
+        self.tick = end;
+        inputs
+    }
+}
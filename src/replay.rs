@@ -0,0 +1,381 @@
+use crate::input::TickInput;
+use crate::levels::Course;
+use crate::world::World;
+use macroquad::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Format version written to new replays. Files from a newer version are
+/// refused rather than played back wrong. Version 2 added expectations,
+/// version 3 the mouse paddle target, version 4 the course, version 5
+/// endless runs.
+pub const VERSION: u32 = 5;
+
+const HEADER_PREFIX: &str = "# rayball replay v";
+
+/// Everything needed to play a session again tick for tick: the world's seed,
+/// arena and course, the input each tick received and, optionally, how the
+/// session ended so a playback can be checked against it.
+///
+/// A campaign started past the first stage is noted as `start <stage>`; a
+/// custom level is stored whole, one `level |<line>` per line of its file; an
+/// endless run is marked `endless`.
+///
+/// Inputs are stored run-length encoded, one line per run of identical ticks:
+/// the repeat count, the held flags (`L`eft, `R`ight, `S` launch, or `-` for
+/// none), if the paddle was dragged, the drag distance and, if the mouse
+/// steered it, `@` and where its center was sent.
+#[derive(Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub arena: Vec2,
+    pub course: Course,
+    pub inputs: Vec<TickInput>,
+    pub expected: Option<Outcome>,
+}
+
+/// Where a session ended up.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outcome {
+    pub score: u32,
+    pub lives: u8,
+    pub stage: usize,
+    /// Hit points left in each brick of the stage, `.` once destroyed.
+    pub bricks: String,
+    pub hash: u64,
+}
+
+impl Outcome {
+    pub fn of(world: &World) -> Self {
+        let bricks = world
+            .bricks()
+            .iter()
+            .map(|b| match b.active {
+                true => char::from_digit(b.hp.into(), 10).unwrap_or('?'),
+                false => '.',
+            })
+            .collect();
+        Outcome {
+            score: world.score,
+            lives: world.lives,
+            stage: world.stage,
+            bricks,
+            hash: world.state_hash(),
+        }
+    }
+
+    /// One line per field that differs from `actual`.
+    pub fn mismatches(&self, actual: &Outcome) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut check = |name: &str, expected: String, got: String| {
+            if expected != got {
+                out.push(format!("{name}: expected {expected}, got {got}"));
+            }
+        };
+        check("score", self.score.to_string(), actual.score.to_string());
+        check("lives", self.lives.to_string(), actual.lives.to_string());
+        check("stage", self.stage.to_string(), actual.stage.to_string());
+        check("bricks", self.bricks.clone(), actual.bricks.clone());
+        check(
+            "hash",
+            format!("{:016x}", self.hash),
+            format!("{:016x}", actual.hash),
+        );
+        out
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, arena: Vec2) -> Self {
+        Replay {
+            seed,
+            arena,
+            course: Course::default(),
+            inputs: Vec::new(),
+            expected: None,
+        }
+    }
+
+    /// Plays the recorded ticks on a fresh world, as fast as possible. Stops
+    /// where the game ends, as a player's game does, even if there is input
+    /// left.
+    pub fn run(&self) -> World {
+        let mut world = self.world();
+        for &input in &self.inputs {
+            world.tick(input);
+            if world.is_over() {
+                break;
+            }
+        }
+        world
+    }
+
+    /// Plays the replay and compares the result with its expectations.
+    /// Returns the differences, or an error if there is nothing to compare.
+    pub fn verify(&self) -> Result<Vec<String>, String> {
+        let expected = self.expected.as_ref().ok_or("no expectations recorded")?;
+        Ok(expected.mismatches(&Outcome::of(&self.run())))
+    }
+
+    /// A fresh world in the state the recording started from.
+    pub fn world(&self) -> World {
+        World::with_course(self.arena, self.seed, self.course.clone())
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let src = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Replay::parse(&src)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{HEADER_PREFIX}{VERSION}\n");
+        out.push_str(&format!("seed {}\n", self.seed));
+        out.push_str(&format!("arena {} {}\n", self.arena.x, self.arena.y));
+        out.push_str(&format!("ticks {}\n", self.inputs.len()));
+        match &self.course {
+            Course::Campaign { first: 0 } => {}
+            Course::Campaign { first } => out.push_str(&format!("start {first}\n")),
+            Course::Custom(src) => {
+                for line in src.lines() {
+                    out.push_str(&format!("level |{line}\n"));
+                }
+            }
+            Course::Endless => out.push_str("endless\n"),
+        }
+        if let Some(expected) = &self.expected {
+            out.push_str(&format!("expect score {}\n", expected.score));
+            out.push_str(&format!("expect lives {}\n", expected.lives));
+            out.push_str(&format!("expect stage {}\n", expected.stage));
+            out.push_str(&format!("expect bricks {}\n", expected.bricks));
+            out.push_str(&format!("expect hash {:016x}\n", expected.hash));
+        }
+
+        let mut runs = self.inputs.chunk_by(|a, b| a == b);
+        for run in &mut runs {
+            let input = run[0];
+            let mut flags = String::new();
+            for (held, flag) in [(input.left, 'L'), (input.right, 'R'), (input.launch, 'S')] {
+                if held {
+                    flags.push(flag);
+                }
+            }
+            if flags.is_empty() {
+                flags.push('-');
+            }
+            out.push_str(&format!("{} {flags}", run.len()));
+            if input.drag != 0. {
+                out.push_str(&format!(" {}", input.drag));
+            }
+            if let Some(x) = input.pointer {
+                out.push_str(&format!(" @{x}"));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn parse(src: &str) -> Result<Replay, String> {
+        // Not trimmed up front: trailing spaces in a level line are empty cells
+        let mut lines = src.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+        let version = lines
+            .next()
+            .and_then(|(_, l)| l.trim().strip_prefix(HEADER_PREFIX))
+            .ok_or("missing replay header")?;
+        if !version.parse().is_ok_and(|v| (1..=VERSION).contains(&v)) {
+            return Err(format!("unsupported replay version `{version}`"));
+        }
+
+        let mut field = |key: &str| -> Result<(usize, Vec<String>), String> {
+            let (line_no, line) = lines.next().ok_or(format!("missing `{key}`"))?;
+            let mut words = line.split_whitespace();
+            if words.next() != Some(key) {
+                return Err(format!("line {line_no}: expected `{key}`"));
+            }
+            Ok((line_no, words.map(str::to_owned).collect()))
+        };
+        let number = |line_no: usize, word: Option<&String>| {
+            word.and_then(|w| w.parse::<f32>().ok())
+                .ok_or(format!("line {line_no}: expected a number"))
+        };
+
+        let (line_no, seed) = field("seed")?;
+        let seed = seed
+            .first()
+            .and_then(|s| s.parse().ok())
+            .ok_or(format!("line {line_no}: invalid seed"))?;
+        let (line_no, arena) = field("arena")?;
+        let arena = vec2(
+            number(line_no, arena.first())?,
+            number(line_no, arena.get(1))?,
+        );
+        let (line_no, ticks) = field("ticks")?;
+        let ticks: usize = ticks
+            .first()
+            .and_then(|s| s.parse().ok())
+            .ok_or(format!("line {line_no}: invalid tick count"))?;
+
+        let mut inputs = Vec::with_capacity(ticks);
+        let mut expect = Vec::new();
+        let mut course = Course::default();
+        let mut level_lines = Vec::new();
+        for (line_no, line) in lines {
+            if let Some(level_line) = line.strip_prefix("level |") {
+                level_lines.push(level_line);
+                continue;
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(first) = line.strip_prefix("start ") {
+                let first = first
+                    .parse()
+                    .ok()
+                    .filter(|&f| f < Course::default().stage_count())
+                    .ok_or(format!("line {line_no}: invalid start stage `{first}`"))?;
+                course = Course::Campaign { first };
+                continue;
+            }
+            if line == "endless" {
+                course = Course::Endless;
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("expect ") {
+                let (key, value) = rest
+                    .split_once(' ')
+                    .ok_or(format!("line {line_no}: expected `expect <key> <value>`"))?;
+                expect.push((line_no, key.to_owned(), value.trim().to_owned()));
+                continue;
+            }
+            let (count, input) =
+                parse_run(line).ok_or(format!("line {line_no}: invalid input `{line}`"))?;
+            inputs.extend(std::iter::repeat_n(input, count));
+        }
+        if inputs.len() != ticks {
+            return Err(format!(
+                "expected {ticks} ticks of input, found {}",
+                inputs.len()
+            ));
+        }
+
+        if !level_lines.is_empty() {
+            course = Course::Custom(level_lines.join("\n"));
+        }
+
+        let expected = if expect.is_empty() {
+            None
+        } else {
+            Some(parse_outcome(&expect)?)
+        };
+
+        Ok(Replay {
+            seed,
+            arena,
+            course,
+            inputs,
+            expected,
+        })
+    }
+}
+
+/// Builds an outcome from `expect` lines, which must give every field.
+fn parse_outcome(lines: &[(usize, String, String)]) -> Result<Outcome, String> {
+    let value = |key: &str| {
+        lines
+            .iter()
+            .find(|(_, k, _)| k == key)
+            .map(|(line_no, _, v)| (*line_no, v.as_str()))
+            .ok_or(format!("missing `expect {key}`"))
+    };
+    let invalid = |line_no: usize, key: &str| format!("line {line_no}: invalid {key}");
+
+    if let Some((line_no, key, _)) = lines
+        .iter()
+        .find(|(_, k, _)| !["score", "lives", "stage", "bricks", "hash"].contains(&k.as_str()))
+    {
+        return Err(format!("line {line_no}: unknown expectation `{key}`"));
+    }
+
+    let (line_no, score) = value("score")?;
+    let score = score.parse().map_err(|_| invalid(line_no, "score"))?;
+    let (line_no, lives) = value("lives")?;
+    let lives = lives.parse().map_err(|_| invalid(line_no, "lives"))?;
+    let (line_no, stage) = value("stage")?;
+    let stage = stage.parse().map_err(|_| invalid(line_no, "stage"))?;
+    let (_, bricks) = value("bricks")?;
+    let (line_no, hash) = value("hash")?;
+    let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid(line_no, "hash"))?;
+
+    Ok(Outcome {
+        score,
+        lives,
+        stage,
+        bricks: bricks.to_owned(),
+        hash,
+    })
+}
+
+/// One `count flags [drag] [@pointer]` line.
+fn parse_run(line: &str) -> Option<(usize, TickInput)> {
+    let mut words = line.split_whitespace().peekable();
+    let count = words.next()?.parse().ok()?;
+    let flags = words.next()?;
+    let drag = match words.next_if(|w| !w.starts_with('@')) {
+        Some(drag) => drag.parse().ok()?,
+        None => 0.,
+    };
+    let pointer = match words.next() {
+        Some(pointer) => Some(pointer.strip_prefix('@')?.parse().ok()?),
+        None => None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+
+    let mut input = TickInput {
+        drag,
+        pointer,
+        ..Default::default()
+    };
+    if flags != "-" {
+        for flag in flags.chars() {
+            match flag {
+                'L' => input.left = true,
+                'R' => input.right = true,
+                'S' => input.launch = true,
+                _ => return None,
+            }
+        }
+    }
+    Some((count, input))
+}
+
+/// A new file in the replay folder for a session with this seed. Named by
+/// the time it is saved, so a fixed seed doesn't overwrite earlier runs.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn recording_path(seed: u64) -> Option<PathBuf> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let dir = dirs::data_dir()?.join("rayball").join("replays");
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| t.as_secs());
+    (0..)
+        .map(|n| match n {
+            0 => dir.join(format!("{time}-{seed}.replay")),
+            n => dir.join(format!("{time}-{seed}-{n}.replay")),
+        })
+        .find(|path| !path.exists())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn recording_path(_seed: u64) -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,99 @@
+use super::menu::Menu;
+use super::{Context, Transition};
+use macroquad::prelude::*;
+use rayball::input::{Action, key_name};
+use rayball::systems::render;
+
+/// Seconds to wait for a key before giving up on rebinding.
+const CAPTURE_TIME: f32 = 5.;
+
+/// Lists every action with its keys. Choosing one waits for a key press,
+/// which is added to the action, or removed if it was already bound.
+#[derive(Default)]
+pub struct ControlsScreen {
+    menu: Menu,
+    /// Action waiting for a key, and how long it will keep waiting.
+    capturing: Option<(Action, f32)>,
+    message: Option<(String, Color)>,
+}
+
+impl ControlsScreen {
+    fn items(ctx: &Context) -> Vec<String> {
+        let mut items: Vec<String> = Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys: Vec<String> = ctx
+                    .bindings
+                    .keys(action)
+                    .iter()
+                    .map(|&k| key_name(k).to_uppercase())
+                    .collect();
+                format!("{:<13}{:>14}", action.title(), keys.join(", "))
+            })
+            .collect();
+        items.push("RESET ALL".to_owned());
+        items.push("BACK".to_owned());
+        items
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if let Some((action, timer)) = &mut self.capturing {
+            let action = *action;
+            *timer -= get_frame_time();
+            if let Some(key) = get_last_key_pressed() {
+                self.capturing = None;
+                self.message = Some(match ctx.bindings.toggle(action, key) {
+                    Ok(()) => (format!("{} UPDATED", action.title()), LIME),
+                    Err(e) => (e, RED),
+                });
+            } else if *timer <= 0. {
+                self.capturing = None;
+                self.message = None;
+            }
+            return Transition::None;
+        }
+
+        if ctx.bindings.pressed(Action::Back) {
+            return self.close(ctx);
+        }
+        let selected = Action::ALL.get(self.menu.selected).copied();
+        if let Some(action) = selected
+            && is_key_pressed(KeyCode::Delete)
+        {
+            ctx.bindings.reset(action);
+            self.message = Some((format!("{} RESET", action.title()), LIME));
+        }
+
+        let count = Action::ALL.len() + 2;
+        match self.menu.update(count, &ctx.bindings) {
+            Some(i) if i < Action::ALL.len() => {
+                let action = Action::ALL[i];
+                self.capturing = Some((action, CAPTURE_TIME));
+                let prompt = format!("PRESS A KEY TO ADD OR REMOVE FOR {}", action.title());
+                self.message = Some((prompt, GOLD));
+            }
+            Some(i) if i == Action::ALL.len() => {
+                ctx.bindings.reset_all();
+                self.message = Some(("ALL CONTROLS RESET".to_owned(), LIME));
+            }
+            Some(_) => return self.close(ctx),
+            None => {}
+        }
+        Transition::None
+    }
+
+    fn close(&self, ctx: &mut Context) -> Transition {
+        ctx.bindings.save();
+        Transition::Pop
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_overlay_background();
+        render::draw_page_title("CONTROLS");
+        self.menu.draw(&Self::items(ctx));
+        if let Some((text, color)) = &self.message {
+            render::draw_message(text, *color);
+        }
+        render::draw_hint("CHOOSE AN ACTION TO ADD OR REMOVE A KEY, DEL RESETS IT");
+    }
+}
@@ -0,0 +1,281 @@
+use super::{Context, Playing, Scene, TitleScreen, Transition};
+use crate::game::Game;
+use macroquad::prelude::*;
+use rayball::components::bricks::{HEIGHT, WIDTH};
+use rayball::components::{Brick, BrickKind};
+use rayball::constants::ARENA;
+use rayball::input::Action;
+use rayball::levels::draft::{COLS, Draft, ROWS};
+use rayball::levels::generator::{self, Difficulty};
+use rayball::levels::parser::Cell;
+use rayball::levels::{Course, Level};
+use rayball::systems::{render, view};
+use std::path::PathBuf;
+
+/// Top of the palette, below the grid.
+const PALETTE_Y: f32 = 470.;
+/// Brick colors to paint with, the campaign's own.
+const COLORS: [u32; 8] = [
+    0xED8796, 0xF5A97F, 0xEED49F, 0xA6DA95, 0x8AADF4, 0xC6A0F6, 0x8BD5CA, 0xFFFFFF,
+];
+/// What testing or saving a level with no bricks says instead.
+const EMPTY_DRAFT: &str = "PAINT SOME BRICKS FIRST";
+
+/// Paints a level on the game's brick grid: left button paints with the
+/// palette pick, right button erases. The level itself lives in the context,
+/// so it survives test runs.
+pub struct EditorScreen {
+    /// Index into `palette()`.
+    selected: usize,
+    /// Hits the normal bricks painted take to break.
+    hp: u8,
+    message: String,
+}
+
+impl Default for EditorScreen {
+    fn default() -> Self {
+        EditorScreen {
+            selected: 0,
+            hp: 1,
+            message: String::new(),
+        }
+    }
+}
+
+impl EditorScreen {
+    /// Back from a test run, saying how it went.
+    pub fn after_test(message: &str) -> Self {
+        EditorScreen {
+            message: message.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// Brick types to pick from: a normal brick in each color, then steel and
+    /// explosive.
+    fn palette(&self) -> Vec<Cell> {
+        let normal = COLORS.map(|hex| Cell {
+            kind: BrickKind::Normal,
+            hp: self.hp,
+            color: Color::from_hex(hex),
+        });
+        let steel = Cell {
+            kind: BrickKind::Steel,
+            hp: 1,
+            color: Color::from_hex(0xA5ADCB),
+        };
+        let explosive = Cell {
+            kind: BrickKind::Explosive,
+            hp: 1,
+            color: Color::from_hex(0xF5A97F),
+        };
+        normal.into_iter().chain([steel, explosive]).collect()
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if ctx.bindings.pressed(Action::Back) {
+            return Transition::Replace(Scene::Title(TitleScreen::default()));
+        }
+        if ctx.bindings.pressed(Action::Confirm) {
+            return self.test_play(ctx);
+        }
+        self.handle_keys(&mut ctx.draft);
+
+        let pos = view::mouse_position();
+        let pressed = is_mouse_button_pressed(MouseButton::Left);
+        if pressed || is_mouse_button_pressed(MouseButton::Right) {
+            ctx.draft.start_stroke();
+        }
+        if pressed && let Some(i) = (0..COLS).find(|&i| palette_rect(i).contains(pos)) {
+            self.selected = i;
+            return Transition::None;
+        }
+        if let Some((row, col)) = Brick::grid_cell(pos, COLS, ARENA.x) {
+            if is_mouse_button_down(MouseButton::Left) {
+                ctx.draft
+                    .paint(row, col, Some(self.palette()[self.selected]));
+            } else if is_mouse_button_down(MouseButton::Right) {
+                ctx.draft.paint(row, col, None);
+            }
+        }
+        Transition::None
+    }
+
+    fn handle_keys(&mut self, draft: &mut Draft) {
+        let ctrl = [
+            KeyCode::LeftControl,
+            KeyCode::RightControl,
+            KeyCode::LeftSuper,
+            KeyCode::RightSuper,
+        ]
+        .into_iter()
+        .any(is_key_down);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+        if ctrl && is_key_pressed(KeyCode::Z) && !shift {
+            if !draft.undo() {
+                self.message = "NOTHING TO UNDO".to_owned();
+            }
+        } else if ctrl && (is_key_pressed(KeyCode::Y) || is_key_pressed(KeyCode::Z)) {
+            if !draft.redo() {
+                self.message = "NOTHING TO REDO".to_owned();
+            }
+        } else if ctrl && is_key_pressed(KeyCode::N) {
+            draft.clear();
+        } else if ctrl && is_key_pressed(KeyCode::S) {
+            self.message = save(draft);
+        } else if ctrl && is_key_pressed(KeyCode::O) {
+            self.message = load(draft);
+        } else if ctrl && is_key_pressed(KeyCode::G) {
+            // Kept short, to be easy to pass to `--generate`
+            let seed = (miniquad::date::now() * 1000.) as u64 % 1_000_000;
+            *draft = Draft::from_level(generator::generate(seed, &Difficulty::default()));
+            self.message = format!("GENERATED FROM SEED {seed}");
+        }
+
+        let digits = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+        if let Some(i) = digits.iter().position(|&k| is_key_pressed(k)) {
+            self.hp = i as u8 + 1;
+            self.message = format!("NORMAL BRICKS TAKE {} HITS", self.hp);
+        }
+    }
+
+    fn test_play(&mut self, ctx: &mut Context) -> Transition {
+        if ctx.draft.is_empty() {
+            self.message = EMPTY_DRAFT.to_owned();
+            return Transition::None;
+        }
+        let course = Course::Custom(ctx.draft.to_text());
+        ctx.start_game(Game::test_play(course));
+        Transition::Reset(Scene::Playing(Playing))
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_page_title("EDITOR");
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                render::draw_slot(slot_rect(row, col), false);
+            }
+        }
+        // Built the way the game builds them, so they look the same
+        for brick in ctx.draft.level.bricks(ARENA.x) {
+            render::brick_draw(&brick);
+        }
+
+        let pos = view::mouse_position();
+        if let Some((row, col)) = Brick::grid_cell(pos, COLS, ARENA.x)
+            && row < ROWS
+        {
+            render::draw_slot(slot_rect(row, col), true);
+        }
+
+        for (i, cell) in self.palette().into_iter().enumerate() {
+            let rect = palette_rect(i);
+            let brick = Brick::new(rect.point(), cell.kind, cell.hp, cell.color);
+            render::brick_draw(&brick);
+            if i == self.selected {
+                render::draw_slot(rect, true);
+            }
+        }
+
+        let status = if self.message.is_empty() {
+            format!("LMB PAINT   1-9 HITS TO BREAK: {}", self.hp)
+        } else {
+            self.message.clone()
+        };
+        render::draw_message(&status, LIGHTGRAY);
+        render::draw_hint(
+            "RMB ERASE  CTRL+Z/Y UNDO/REDO  CTRL+S/O SAVE/LOAD  CTRL+N/G NEW/RANDOM  ENTER TEST",
+        );
+    }
+}
+
+fn slot_rect(row: usize, col: usize) -> Rect {
+    let pos = Brick::grid_pos(row, col, COLS, ARENA.x);
+    Rect::new(pos.x, pos.y, WIDTH, HEIGHT)
+}
+
+/// Palette entries sit in a row under the grid, one per column.
+fn palette_rect(i: usize) -> Rect {
+    let mut rect = slot_rect(0, i);
+    rect.y = PALETTE_Y;
+    rect
+}
+
+fn save(draft: &Draft) -> String {
+    // It would only fail to load again
+    if draft.is_empty() {
+        return EMPTY_DRAFT.to_owned();
+    }
+    let Some(path) = draft_path() else {
+        return "SAVING NOT AVAILABLE".to_owned();
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, draft.to_text()));
+    match written {
+        Ok(()) => {
+            log::info!("Saved level to {}", path.display());
+            format!("SAVED {}", path.display())
+        }
+        Err(e) => {
+            log::warn!("Could not save level to {}: {e}", path.display());
+            "COULD NOT SAVE, SEE LOG".to_owned()
+        }
+    }
+}
+
+fn load(draft: &mut Draft) -> String {
+    let Some(path) = draft_path() else {
+        return "LOADING NOT AVAILABLE".to_owned();
+    };
+    let src = match std::fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(e) => {
+            log::warn!("Could not read {}: {e}", path.display());
+            return "NO SAVED LEVEL".to_owned();
+        }
+    };
+    match Level::parse(&src) {
+        Ok(level) => {
+            *draft = Draft::from_level(level);
+            format!("LOADED {}", path.display())
+        }
+        Err(e) => {
+            log::warn!("Could not load {}: {e}", path.display());
+            format!("BROKEN LEVEL FILE: {e}")
+        }
+    }
+}
+
+/// Where the editor saves its level. Play it with `--level-file`.
+#[cfg(not(target_arch = "wasm32"))]
+fn draft_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("rayball").join("levels").join("editor.lvl"))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn draft_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_level_is_not_saved() {
+        assert_eq!(save(&Draft::default()), EMPTY_DRAFT);
+    }
+}
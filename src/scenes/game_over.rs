@@ -0,0 +1,66 @@
+use super::menu::Menu;
+use super::{Context, HighScoreScreen, NameEntryScreen, Playing, Scene, TitleScreen, Transition};
+use macroquad::prelude::*;
+use rayball::input::Action;
+use rayball::systems::render;
+
+const ITEMS: [&str; 3] = ["PLAY AGAIN", "HIGH SCORES", "MAIN MENU"];
+
+/// End of a run, lost or won. Asks for a name first if the score made the table.
+pub struct GameOverScreen {
+    won: bool,
+    menu: Menu,
+    checked_score: bool,
+}
+
+impl GameOverScreen {
+    pub fn new(won: bool) -> Self {
+        GameOverScreen {
+            won,
+            menu: Menu::default(),
+            checked_score: false,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        let Some(game) = &ctx.game else {
+            return Transition::Reset(Scene::Title(TitleScreen::default()));
+        };
+        let (mode, score) = (game.mode, game.world.score);
+        if !self.checked_score {
+            self.checked_score = true;
+            // Runs started on a later stage or a fixed seed don't rank
+            if ctx.setup.ranks(mode) && ctx.high_scores.qualifies(mode, score) {
+                return Transition::Push(Scene::NameEntry(NameEntryScreen::new(mode, score)));
+            }
+        }
+
+        if ctx.bindings.pressed(Action::Back) {
+            ctx.end_game();
+            return Transition::Reset(Scene::Title(TitleScreen::default()));
+        }
+        match self.menu.update(ITEMS.len(), &ctx.bindings) {
+            Some(0) => {
+                ctx.start_game(game.restart(&ctx.setup));
+                Transition::Reset(Scene::Playing(Playing))
+            }
+            Some(1) => Transition::Push(Scene::HighScores(HighScoreScreen::new(mode, None))),
+            Some(2) => {
+                ctx.end_game();
+                Transition::Reset(Scene::Title(TitleScreen::default()))
+            }
+            _ => Transition::None,
+        }
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        let score = ctx.game.as_ref().map_or(0, |g| g.world.score);
+        render::draw_overlay_background();
+        if self.won {
+            render::draw_heading("GAME CLEARED", &format!("{score}"), LIME);
+        } else {
+            render::draw_heading("GAME OVER", &format!("{score}"), RED);
+        }
+        self.menu.draw(&ITEMS.map(String::from));
+    }
+}
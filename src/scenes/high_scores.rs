@@ -0,0 +1,50 @@
+use super::{Context, Transition, confirm_pressed};
+use rayball::input::Action;
+use rayball::systems::highscore::GameMode;
+use rayball::systems::render;
+
+pub struct HighScoreScreen {
+    mode: GameMode,
+    /// Highlights the entry at this rank, if the player just set it.
+    highlight: Option<usize>,
+}
+
+impl HighScoreScreen {
+    pub fn new(mode: GameMode, highlight: Option<usize>) -> Self {
+        HighScoreScreen { mode, highlight }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if confirm_pressed(&ctx.bindings) || ctx.bindings.pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        // Left and right flip through the other modes' tables
+        let step = if ctx.bindings.pressed(Action::MoveLeft) {
+            GameMode::ALL.len() - 1
+        } else if ctx.bindings.pressed(Action::MoveRight) {
+            1
+        } else {
+            return Transition::None;
+        };
+        let i = GameMode::ALL
+            .iter()
+            .position(|&m| m == self.mode)
+            .unwrap_or(0);
+        self.mode = GameMode::ALL[(i + step) % GameMode::ALL.len()];
+        self.highlight = None;
+        Transition::None
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_high_scores(
+            self.mode.title(),
+            ctx.high_scores.table(self.mode),
+            self.highlight,
+            [
+                &ctx.bindings.label(Action::MoveLeft),
+                &ctx.bindings.label(Action::MoveRight),
+                &ctx.bindings.label(Action::Confirm),
+            ],
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use super::{Context, Transition, confirm_pressed};
+use macroquad::prelude::*;
+use rayball::input::Action;
+use rayball::systems::render;
+
+/// Moves on by itself after this many seconds.
+const SHOW_TIME: f32 = 3.;
+
+/// Shown between stages, with the next one already loaded underneath.
+pub struct LevelCompleteScreen {
+    /// Number of the stage just cleared, from 1.
+    number: usize,
+    timer: f32,
+}
+
+impl LevelCompleteScreen {
+    pub fn new(number: usize) -> Self {
+        LevelCompleteScreen { number, timer: 0. }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        self.timer += get_frame_time();
+        if confirm_pressed(&ctx.bindings) || self.timer >= SHOW_TIME {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        let score = ctx.game.as_ref().map_or(0, |g| g.world.score);
+        render::draw_level_complete(self.number, score, &ctx.bindings.label(Action::Confirm));
+    }
+}
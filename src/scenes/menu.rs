@@ -0,0 +1,50 @@
+use macroquad::prelude::*;
+use rayball::input::{Action, Bindings};
+use rayball::systems::{render, view};
+
+/// A vertical list of choices, picked with the arrow keys, the mouse or a tap.
+#[derive(Default)]
+pub struct Menu {
+    pub selected: usize,
+}
+
+impl Menu {
+    /// Moves the selection and returns the item chosen this frame, if any.
+    pub fn update(&mut self, count: usize, bindings: &Bindings) -> Option<usize> {
+        if bindings.pressed(Action::MenuUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if bindings.pressed(Action::MenuDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if bindings.pressed(Action::Confirm) {
+            return Some(self.selected);
+        }
+
+        let item_at =
+            |pos: Vec2| (0..count).find(|&i| render::menu_item_rect(i, count).contains(pos));
+        if mouse_delta_position() != Vec2::ZERO
+            && let Some(i) = item_at(view::mouse_position())
+        {
+            self.selected = i;
+        }
+        if is_mouse_button_pressed(MouseButton::Left)
+            && let Some(i) = item_at(view::mouse_position())
+        {
+            self.selected = i;
+            return Some(i);
+        }
+        if let Some(touch) = touches().first()
+            && touch.phase == TouchPhase::Ended
+            && let Some(i) = item_at(view::touch_position(touch))
+        {
+            self.selected = i;
+            return Some(i);
+        }
+        None
+    }
+
+    pub fn draw(&self, items: &[String]) {
+        render::draw_menu(items, self.selected);
+    }
+}
@@ -0,0 +1,140 @@
+//! Screens the game moves between. They sit on a stack: the top one gets
+//! input and draws over the run in progress, if there is one.
+
+pub mod controls;
+pub mod editor;
+pub mod game_over;
+pub mod high_scores;
+pub mod level_complete;
+pub mod menu;
+pub mod name_entry;
+pub mod paused;
+pub mod playing;
+pub mod settings;
+pub mod title;
+
+use crate::game::{Game, Setup};
+use macroquad::prelude::*;
+use rayball::input::{Action, Bindings};
+use rayball::levels::draft::Draft;
+use rayball::settings::Settings;
+use rayball::systems::audio::SoundManager;
+use rayball::systems::highscore::HighScores;
+
+pub use controls::ControlsScreen;
+pub use editor::EditorScreen;
+pub use game_over::GameOverScreen;
+pub use high_scores::HighScoreScreen;
+pub use level_complete::LevelCompleteScreen;
+pub use name_entry::NameEntryScreen;
+pub use paused::PauseMenu;
+pub use playing::Playing;
+pub use settings::SettingsScreen;
+pub use title::TitleScreen;
+
+/// What scenes share: the run in progress and everything outliving it.
+pub struct Context {
+    pub game: Option<Game>,
+    pub sounds: SoundManager,
+    pub high_scores: HighScores,
+    pub bindings: Bindings,
+    pub settings: Settings,
+    pub setup: Setup,
+    /// The level open in the editor, kept while it is test played.
+    pub draft: Draft,
+    /// Muted for this session only, from the command line.
+    pub muted: bool,
+}
+
+impl Context {
+    /// Replaces the run in progress, saving its recording.
+    pub fn start_game(&mut self, game: Game) {
+        self.end_game();
+        self.game = Some(game);
+    }
+
+    /// Drops the run in progress, saving its recording. The one place
+    /// recordings are saved, so each run is written once.
+    pub fn end_game(&mut self) {
+        if let Some(mut game) = self.game.take()
+            && self.settings.gameplay.save_replays
+            && !game.test_play
+        {
+            game.save_recording();
+        }
+    }
+
+    /// Passes the settings on to whatever uses them outside of scenes.
+    pub fn apply_settings(&mut self) {
+        self.sounds.muted = self.muted || self.settings.audio.muted;
+        self.sounds.volume = self.settings.audio.volume;
+    }
+}
+
+pub enum Scene {
+    Title(TitleScreen),
+    Playing(Playing),
+    Paused(PauseMenu),
+    LevelComplete(LevelCompleteScreen),
+    GameOver(GameOverScreen),
+    Settings(SettingsScreen),
+    Controls(ControlsScreen),
+    HighScores(HighScoreScreen),
+    NameEntry(NameEntryScreen),
+    Editor(EditorScreen),
+}
+
+/// What the scene stack should do after a scene's update.
+pub enum Transition {
+    None,
+    Push(Scene),
+    Pop,
+    Replace(Scene),
+    /// Drops every scene and starts over from this one.
+    Reset(Scene),
+    Quit,
+}
+
+impl Scene {
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        match self {
+            Scene::Title(s) => s.update(ctx),
+            Scene::Playing(s) => s.update(ctx),
+            Scene::Paused(s) => s.update(ctx),
+            Scene::LevelComplete(s) => s.update(ctx),
+            Scene::GameOver(s) => s.update(ctx),
+            Scene::Settings(s) => s.update(ctx),
+            Scene::Controls(s) => s.update(ctx),
+            Scene::HighScores(s) => s.update(ctx),
+            Scene::NameEntry(s) => s.update(ctx),
+            Scene::Editor(s) => s.update(ctx),
+        }
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        match self {
+            Scene::Title(s) => s.draw(),
+            Scene::Playing(_) => {}
+            Scene::Paused(s) => s.draw(ctx),
+            Scene::LevelComplete(s) => s.draw(ctx),
+            Scene::GameOver(s) => s.draw(ctx),
+            Scene::Settings(s) => s.draw(ctx),
+            Scene::Controls(s) => s.draw(ctx),
+            Scene::HighScores(s) => s.draw(ctx),
+            Scene::NameEntry(s) => s.draw(ctx),
+            Scene::Editor(s) => s.draw(ctx),
+        }
+    }
+}
+
+/// Whether a finger was just lifted, the touch version of a click.
+pub fn tapped() -> bool {
+    touches()
+        .first()
+        .is_some_and(|t| t.phase == TouchPhase::Ended)
+}
+
+/// Confirm or a tap: how a player moves past a screen.
+pub fn confirm_pressed(bindings: &Bindings) -> bool {
+    bindings.pressed(Action::Confirm) || tapped()
+}
@@ -0,0 +1,50 @@
+use super::{Context, HighScoreScreen, Scene, Transition, confirm_pressed};
+use macroquad::prelude::*;
+use rayball::input::Action;
+use rayball::systems::highscore::{GameMode, MAX_NAME_LEN};
+use rayball::systems::render;
+
+pub struct NameEntryScreen {
+    mode: GameMode,
+    score: u32,
+    name: String,
+}
+
+impl NameEntryScreen {
+    pub fn new(mode: GameMode, score: u32) -> Self {
+        // Drop whatever was typed during play
+        while get_char_pressed().is_some() {}
+        NameEntryScreen {
+            mode,
+            score,
+            name: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        let mut typed = false;
+        while let Some(c) = get_char_pressed() {
+            let allowed = c.is_ascii_alphanumeric() || c == ' ';
+            typed |= allowed;
+            if allowed && self.name.len() < MAX_NAME_LEN {
+                self.name.push(c.to_ascii_uppercase());
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.name.pop();
+        }
+        if ctx.bindings.pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        // A confirm key that types, like Space, goes into the name instead
+        if confirm_pressed(&ctx.bindings) && !typed {
+            let rank = ctx.high_scores.insert(self.mode, &self.name, self.score);
+            return Transition::Replace(Scene::HighScores(HighScoreScreen::new(self.mode, rank)));
+        }
+        Transition::None
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_name_entry(self.score, &self.name, &ctx.bindings.label(Action::Confirm));
+    }
+}
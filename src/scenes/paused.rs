@@ -0,0 +1,51 @@
+use super::menu::Menu;
+use super::{Context, EditorScreen, Playing, Scene, SettingsScreen, TitleScreen, Transition};
+use macroquad::prelude::*;
+use rayball::input::Action;
+use rayball::systems::render;
+
+const ITEMS: [&str; 5] = ["RESUME", "RESTART", "SETTINGS", "MAIN MENU", "QUIT"];
+
+#[derive(Default)]
+pub struct PauseMenu {
+    menu: Menu,
+}
+
+impl PauseMenu {
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if ctx.bindings.pressed(Action::Back) {
+            return Transition::Pop;
+        }
+        match self.menu.update(ITEMS.len(), &ctx.bindings) {
+            Some(0) => Transition::Pop,
+            Some(1) => {
+                if let Some(game) = &ctx.game {
+                    let restarted = game.restart(&ctx.setup);
+                    ctx.start_game(restarted);
+                }
+                Transition::Reset(Scene::Playing(Playing))
+            }
+            Some(2) => Transition::Push(Scene::Settings(SettingsScreen::default())),
+            Some(3) => {
+                let test_play = ctx.game.as_ref().is_some_and(|g| g.test_play);
+                ctx.end_game();
+                if test_play {
+                    return Transition::Reset(Scene::Editor(EditorScreen::default()));
+                }
+                Transition::Reset(Scene::Title(TitleScreen::default()))
+            }
+            Some(4) => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_overlay_background();
+        render::draw_heading("PAUSED", "", WHITE);
+        let mut items = ITEMS.map(String::from);
+        if ctx.game.as_ref().is_some_and(|g| g.test_play) {
+            items[3] = "EDITOR".to_owned();
+        }
+        self.menu.draw(&items);
+    }
+}
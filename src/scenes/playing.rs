@@ -0,0 +1,50 @@
+use super::{
+    Context, EditorScreen, GameOverScreen, LevelCompleteScreen, PauseMenu, Scene, TitleScreen,
+    Transition,
+};
+use rayball::input::Action;
+use rayball::world::GameEvent;
+
+/// The run itself. Draws nothing of its own, the run is drawn under every scene.
+pub struct Playing;
+
+impl Playing {
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        let Some(game) = &mut ctx.game else {
+            return Transition::Replace(Scene::Title(TitleScreen::default()));
+        };
+        if ctx.bindings.pressed(Action::Pause) {
+            return Transition::Push(Scene::Paused(PauseMenu::default()));
+        }
+
+        let events = game.update(&mut ctx.sounds, &ctx.bindings, ctx.settings.controls.mouse);
+        if game.is_replay() {
+            // Replays play straight through, the viewer has its own controls
+            return Transition::None;
+        }
+        for event in events {
+            match event {
+                GameEvent::StageStarted(stage) => {
+                    // Stages count from 1 on screen, so this is the one cleared
+                    return Transition::Push(Scene::LevelComplete(LevelCompleteScreen::new(stage)));
+                }
+                GameEvent::GameOver | GameEvent::GameWon if game.test_play => {
+                    let message = if matches!(event, GameEvent::GameWon) {
+                        "TEST RUN CLEARED"
+                    } else {
+                        "TEST RUN LOST"
+                    };
+                    ctx.end_game();
+                    return Transition::Reset(Scene::Editor(EditorScreen::after_test(message)));
+                }
+                // The recording is saved when the game is ended from the next screen
+                GameEvent::GameOver | GameEvent::GameWon => {
+                    let won = matches!(event, GameEvent::GameWon);
+                    return Transition::Replace(Scene::GameOver(GameOverScreen::new(won)));
+                }
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+}
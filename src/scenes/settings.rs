@@ -0,0 +1,148 @@
+use super::menu::Menu;
+use super::{Context, ControlsScreen, Scene, Transition};
+use macroquad::prelude::*;
+use rayball::input::Action;
+use rayball::settings::{SENSITIVITY_RANGE, SMOOTHING_RANGE, VOLUME_RANGE};
+use rayball::systems::render;
+
+const VOLUME_STEP: f32 = 0.1;
+const SENSITIVITY_STEP: f32 = 0.25;
+const SMOOTHING_STEP: f32 = 0.1;
+/// Frame rate limits to pick from, 0 for none.
+const FPS_CAPS: [u32; 6] = [30, 60, 120, 144, 240, 0];
+
+const SOUND: usize = 0;
+const VOLUME: usize = 1;
+const PADDLE: usize = 2;
+const SENSITIVITY: usize = 3;
+const SMOOTHING: usize = 4;
+const FULLSCREEN: usize = 5;
+const FPS_CAP: usize = 6;
+const REPLAYS: usize = 7;
+const CONTROLS: usize = 8;
+const BACK: usize = 9;
+
+#[derive(Default)]
+pub struct SettingsScreen {
+    menu: Menu,
+}
+
+impl SettingsScreen {
+    fn items(ctx: &Context) -> Vec<String> {
+        let settings = &ctx.settings;
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        let mouse = &settings.controls.mouse;
+        let paddle = if mouse.enabled { "MOUSE" } else { "KEYS" };
+        let fps_cap = match settings.display.fps_cap {
+            0 => "NONE".to_owned(),
+            fps => fps.to_string(),
+        };
+        vec![
+            format!("SOUND: {}", on_off(!settings.audio.muted)),
+            format!("VOLUME: {:.0}%", settings.audio.volume * 100.),
+            format!("PADDLE: {paddle}"),
+            format!("MOUSE SPEED: {:.2}", mouse.sensitivity),
+            format!("MOUSE SMOOTHING: {:.1}", mouse.smoothing),
+            format!("FULLSCREEN: {}", on_off(settings.display.fullscreen)),
+            format!("FPS LIMIT: {fps_cap}"),
+            format!("SAVE REPLAYS: {}", on_off(settings.gameplay.save_replays)),
+            "CONTROLS".to_owned(),
+            "BACK".to_owned(),
+        ]
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if ctx.bindings.pressed(Action::Back) {
+            return Self::close(ctx);
+        }
+
+        // Left and right step the selected value down and up
+        let step = match (
+            ctx.bindings.pressed(Action::MoveLeft),
+            ctx.bindings.pressed(Action::MoveRight),
+        ) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        if step != 0 {
+            Self::adjust(ctx, self.menu.selected, step);
+        }
+
+        match self.menu.update(Self::items(ctx).len(), &ctx.bindings) {
+            Some(CONTROLS) => Transition::Push(Scene::Controls(ControlsScreen::default())),
+            Some(BACK) => Self::close(ctx),
+            Some(item) => {
+                Self::adjust(ctx, item, 1);
+                Transition::None
+            }
+            None => Transition::None,
+        }
+    }
+
+    fn close(ctx: &mut Context) -> Transition {
+        ctx.settings.save();
+        Transition::Pop
+    }
+
+    /// Changes the value on row `item` by `step` notches. Values wrap around
+    /// when confirmed past their end, so every row works with one button.
+    fn adjust(ctx: &mut Context, item: usize, step: i32) {
+        let settings = &mut ctx.settings;
+        let mouse = &mut settings.controls.mouse;
+        let notches = step as f32;
+        match item {
+            SOUND => settings.audio.muted = !settings.audio.muted,
+            VOLUME => {
+                let volume = notch(settings.audio.volume, notches * VOLUME_STEP, VOLUME_RANGE);
+                settings.audio.volume = volume;
+            }
+            PADDLE => mouse.enabled = !mouse.enabled,
+            SENSITIVITY => {
+                let delta = notches * SENSITIVITY_STEP;
+                mouse.sensitivity = notch(mouse.sensitivity, delta, SENSITIVITY_RANGE);
+            }
+            SMOOTHING => {
+                let delta = notches * SMOOTHING_STEP;
+                mouse.smoothing = notch(mouse.smoothing, delta, SMOOTHING_RANGE);
+            }
+            FULLSCREEN => {
+                settings.display.fullscreen = !settings.display.fullscreen;
+                set_fullscreen(settings.display.fullscreen);
+            }
+            FPS_CAP => {
+                let current = FPS_CAPS.iter().position(|&f| f == settings.display.fps_cap);
+                let next = match current {
+                    Some(i) => (i as i32 + step).rem_euclid(FPS_CAPS.len() as i32),
+                    None => 0,
+                };
+                settings.display.fps_cap = FPS_CAPS[next as usize];
+            }
+            REPLAYS => settings.gameplay.save_replays = !settings.gameplay.save_replays,
+            _ => {}
+        }
+        ctx.apply_settings();
+    }
+
+    pub fn draw(&self, ctx: &Context) {
+        render::draw_overlay_background();
+        render::draw_page_title("SETTINGS");
+        self.menu.draw(&Self::items(ctx));
+        render::draw_hint("LEFT / RIGHT TO CHANGE");
+    }
+}
+
+/// `value` moved by `delta`, wrapping to the other end of `(min, max)` when
+/// it runs past one.
+fn notch(value: f32, delta: f32, (min, max): (f32, f32)) -> f32 {
+    let next = value + delta;
+    // Allow a little rounding error before wrapping
+    if next > max + 0.001 {
+        min
+    } else if next < min - 0.001 {
+        max
+    } else {
+        // Keep repeated steps from drifting off round numbers in the file
+        ((next * 100.).round() / 100.).clamp(min, max)
+    }
+}
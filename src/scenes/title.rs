@@ -0,0 +1,51 @@
+use super::menu::Menu;
+use super::{Context, EditorScreen, HighScoreScreen, Playing, Scene, SettingsScreen, Transition};
+use crate::game::Game;
+use rayball::input::Action;
+use rayball::systems::highscore::GameMode;
+use rayball::systems::render;
+
+const ITEMS: [&str; 6] = [
+    "PLAY",
+    "ENDLESS",
+    "HIGH SCORES",
+    "EDITOR",
+    "SETTINGS",
+    "QUIT",
+];
+
+#[derive(Default)]
+pub struct TitleScreen {
+    menu: Menu,
+}
+
+impl TitleScreen {
+    pub fn update(&mut self, ctx: &mut Context) -> Transition {
+        if ctx.bindings.pressed(Action::Back) {
+            return Transition::Quit;
+        }
+        match self.menu.update(ITEMS.len(), &ctx.bindings) {
+            Some(0) => {
+                ctx.start_game(Game::new(&ctx.setup));
+                Transition::Replace(Scene::Playing(Playing))
+            }
+            Some(1) => {
+                ctx.start_game(Game::new(&ctx.setup.endless()));
+                Transition::Replace(Scene::Playing(Playing))
+            }
+            Some(2) => Transition::Push(Scene::HighScores(HighScoreScreen::new(
+                GameMode::Campaign,
+                None,
+            ))),
+            Some(3) => Transition::Replace(Scene::Editor(EditorScreen::default())),
+            Some(4) => Transition::Push(Scene::Settings(SettingsScreen::default())),
+            Some(5) => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+
+    pub fn draw(&self) {
+        render::draw_title();
+        self.menu.draw(&ITEMS.map(String::from));
+    }
+}
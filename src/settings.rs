@@ -0,0 +1,409 @@
+//! What the player chose in the settings screen, kept between sessions as
+//! TOML in the platform config directory on native builds.
+
+use crate::input::MouseConfig;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::path::PathBuf;
+
+const HEADER: &str = "# rayball settings";
+
+pub const VOLUME_RANGE: (f32, f32) = (0., 1.);
+pub const SENSITIVITY_RANGE: (f32, f32) = (0.25, 4.);
+pub const SMOOTHING_RANGE: (f32, f32) = (0., 0.9);
+pub const WIDTH_RANGE: (u32, u32) = (320, 7680);
+pub const HEIGHT_RANGE: (u32, u32) = (240, 4320);
+/// Slowest and fastest frame rate limit; 0 turns the limit off.
+pub const FPS_CAP_RANGE: (u32, u32) = (15, 1000);
+
+/// Every setting, grouped the way the file is. Missing keys, and values of
+/// the wrong type or out of range, fall back to the defaults. Keys this
+/// version doesn't know are left alone and written back on save, so a file
+/// shared with a newer build keeps its extra settings.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: Audio,
+    pub controls: Controls,
+    pub display: Display,
+    pub gameplay: Gameplay,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// The file as it was read, unknown keys and all.
+    #[serde(skip)]
+    file: toml::Table,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Audio {
+    pub muted: bool,
+    #[serde(serialize_with = "short_float")]
+    pub volume: f32,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio {
+            muted: false,
+            volume: 1.,
+        }
+    }
+}
+
+/// Key bindings live in their own file, see [`crate::input::Bindings`].
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    pub mouse: MouseConfig,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Display {
+    /// Window size in logical pixels.
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    /// Frames per second the game is held to, 0 for no limit.
+    pub fps_cap: u32,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            fps_cap: 120,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gameplay {
+    /// Record every session to the replay folder.
+    pub save_replays: bool,
+}
+
+impl Default for Gameplay {
+    fn default() -> Self {
+        Gameplay { save_replays: true }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let path = settings_path();
+        let defaults = Settings {
+            path: path.clone(),
+            ..Default::default()
+        };
+        let Some(path) = path else {
+            return defaults;
+        };
+        let src = match std::fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return defaults,
+            Err(e) => {
+                log::warn!("Could not read settings from {}: {e}", path.display());
+                return defaults;
+            }
+        };
+
+        match Settings::parse(&src) {
+            Ok((settings, warnings)) => {
+                for warning in warnings {
+                    log::warn!("{}: {warning}", path.display());
+                }
+                log::info!("Loaded settings from {}", path.display());
+                Settings {
+                    path: Some(path),
+                    ..settings
+                }
+            }
+            Err(e) => {
+                log::warn!("Ignoring unreadable settings file {}: {e}", path.display());
+                defaults
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let out = self.to_text();
+
+        if let Some(dir) = path.parent()
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            log::warn!("Could not create {}: {e}", dir.display());
+            return;
+        }
+        match std::fs::write(path, out) {
+            Ok(()) => log::info!("Saved settings to {}", path.display()),
+            Err(e) => log::warn!("Could not save settings to {}: {e}", path.display()),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut table = self.file.clone();
+        overlay(&mut table, to_table(self));
+        let body = toml::to_string(&table).unwrap_or_else(|e| {
+            log::warn!("Could not write settings: {e}");
+            String::new()
+        });
+        format!("{HEADER}\n\n{body}")
+    }
+
+    /// Reads settings written by [`Settings::to_text`], or by hand. Only
+    /// text that isn't TOML at all is an error; anything else wrong is
+    /// replaced by its default and reported in the returned warnings.
+    pub fn parse(src: &str) -> Result<(Settings, Vec<String>), String> {
+        let file: toml::Table = src
+            .parse()
+            .map_err(|e: toml::de::Error| e.message().to_owned())?;
+        let mut warnings = Vec::new();
+        let mut settings: Settings = merge(&Settings::default(), &file, &mut warnings);
+        settings.validate(&mut warnings);
+        settings.file = file;
+        Ok((settings, warnings))
+    }
+
+    fn validate(&mut self, warnings: &mut Vec<String>) {
+        let defaults = Settings::default();
+        let mouse = &mut self.controls.mouse;
+        let display = &mut self.display;
+        check_range(
+            "audio.volume",
+            &mut self.audio.volume,
+            defaults.audio.volume,
+            VOLUME_RANGE,
+            warnings,
+        );
+        check_range(
+            "controls.mouse.sensitivity",
+            &mut mouse.sensitivity,
+            defaults.controls.mouse.sensitivity,
+            SENSITIVITY_RANGE,
+            warnings,
+        );
+        check_range(
+            "controls.mouse.smoothing",
+            &mut mouse.smoothing,
+            defaults.controls.mouse.smoothing,
+            SMOOTHING_RANGE,
+            warnings,
+        );
+        check_range(
+            "display.width",
+            &mut display.width,
+            defaults.display.width,
+            WIDTH_RANGE,
+            warnings,
+        );
+        check_range(
+            "display.height",
+            &mut display.height,
+            defaults.display.height,
+            HEIGHT_RANGE,
+            warnings,
+        );
+        if display.fps_cap != 0 {
+            check_range(
+                "display.fps_cap",
+                &mut display.fps_cap,
+                defaults.display.fps_cap,
+                FPS_CAP_RANGE,
+                warnings,
+            );
+        }
+    }
+}
+
+/// Resets `value` to `default` if it's outside `min..=max`, NaN included.
+fn check_range<T: PartialOrd + Copy + fmt::Display>(
+    key: &str,
+    value: &mut T,
+    default: T,
+    (min, max): (T, T),
+    warnings: &mut Vec<String>,
+) {
+    if !(min..=max).contains(value) {
+        warnings.push(format!(
+            "`{key}` must be between {min} and {max}, not {value}; using {default}"
+        ));
+        *value = default;
+    }
+}
+
+/// `defaults` with every key of `file` that fits laid over it, one key at a
+/// time so a bad value only costs its own setting.
+fn merge<T>(defaults: &T, file: &toml::Table, warnings: &mut Vec<String>) -> T
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    let mut merged = to_table(defaults);
+    for path in leaf_paths(&merged) {
+        let Some(value) = lookup(file, &path) else {
+            continue;
+        };
+        let old = replace(&mut merged, &path, value.clone());
+        if toml::Value::Table(merged.clone()).try_into::<T>().is_err() {
+            replace(&mut merged, &path, old);
+            warnings.push(format!(
+                "`{}` can't be {value}; using the default",
+                path.join(".")
+            ));
+        }
+    }
+    toml::Value::Table(merged)
+        .try_into()
+        .unwrap_or_else(|_| defaults.clone())
+}
+
+fn to_table<T: Serialize>(value: &T) -> toml::Table {
+    match toml::Value::try_from(value) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    }
+}
+
+/// Key paths to every value in `table` that isn't itself a table.
+fn leaf_paths(table: &toml::Table) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(inner) => {
+                for mut path in leaf_paths(inner) {
+                    path.insert(0, key.clone());
+                    paths.push(path);
+                }
+            }
+            _ => paths.push(vec![key.clone()]),
+        }
+    }
+    paths
+}
+
+fn lookup<'a>(table: &'a toml::Table, path: &[String]) -> Option<&'a toml::Value> {
+    let (last, parents) = path.split_last()?;
+    let mut table = table;
+    for key in parents {
+        table = table.get(key)?.as_table()?;
+    }
+    table.get(last)
+}
+
+/// Puts `value` at `path`, which must exist, and returns what was there.
+fn replace(table: &mut toml::Table, path: &[String], value: toml::Value) -> toml::Value {
+    let (last, parents) = path.split_last().expect("empty settings path");
+    let mut table = table;
+    for key in parents {
+        table = table
+            .get_mut(key)
+            .and_then(toml::Value::as_table_mut)
+            .expect("settings path leads through tables");
+    }
+    table
+        .insert(last.clone(), value)
+        .expect("settings path exists")
+}
+
+/// Writes every value of `ours` into `table`, keeping what only `table` has.
+fn overlay(table: &mut toml::Table, ours: toml::Table) {
+    for (key, value) in ours {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(inner)) => {
+                overlay(existing, inner)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Writes an `f32` with as few digits as read back the same, so 0.3 isn't
+/// saved as 0.30000001192092896.
+pub fn short_float<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    let short: f64 = value.to_string().parse().unwrap_or(f64::from(*value));
+    serializer.serialize_f64(short)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> Option<PathBuf> {
+    let path = dirs::config_dir().map(|d| d.join("rayball").join("settings.toml"));
+    if path.is_none() {
+        log::warn!("No config directory found, settings won't be saved");
+    }
+    path
+}
+
+#[cfg(target_arch = "wasm32")]
+fn settings_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_round_trip() {
+        let settings = Settings::default();
+        let (parsed, warnings) = Settings::parse(&settings.to_text()).unwrap();
+        assert_eq!(parsed.audio, settings.audio);
+        assert_eq!(parsed.controls, settings.controls);
+        assert_eq!(parsed.display, settings.display);
+        assert_eq!(parsed.gameplay, settings.gameplay);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn missing_keys_keep_their_defaults() {
+        let (settings, warnings) = Settings::parse("[audio]\nvolume = 0.5\n").unwrap();
+        assert_eq!(settings.audio.volume, 0.5);
+        assert!(!settings.audio.muted);
+        assert_eq!(settings.display, Display::default());
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn bad_values_only_reset_themselves() {
+        let src = "[audio]\nmuted = \"yes\"\nvolume = 3.0\n\n[display]\nwidth = 1024\n";
+        let (settings, warnings) = Settings::parse(src).unwrap();
+        assert!(!settings.audio.muted);
+        assert_eq!(settings.audio.volume, Audio::default().volume);
+        assert_eq!(settings.display.width, 1024);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+    }
+
+    #[test]
+    fn unknown_keys_survive_a_save() {
+        let src = "[audio]\nreverb = true\n\n[network]\nport = 4000\n";
+        let (mut settings, warnings) = Settings::parse(src).unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        settings.audio.muted = true;
+
+        let (saved, _) = Settings::parse(&settings.to_text()).unwrap();
+        assert!(saved.audio.muted);
+        assert_eq!(saved.file["audio"]["reverb"].as_bool(), Some(true));
+        assert_eq!(saved.file["network"]["port"].as_integer(), Some(4000));
+    }
+
+    #[test]
+    fn floats_are_written_short() {
+        let mut settings = Settings::default();
+        settings.controls.mouse.smoothing = 0.3;
+        assert!(settings.to_text().contains("smoothing = 0.3\n"));
+    }
+
+    #[test]
+    fn text_that_isnt_toml_is_an_error() {
+        assert!(Settings::parse("volume = = 1").is_err());
+    }
+}
@@ -1,10 +1,13 @@
-use macroquad::audio::{Sound, load_sound_from_bytes, play_sound_once};
+use macroquad::audio::{PlaySoundParams, Sound, load_sound_from_bytes, play_sound};
 use macroquad::prelude::get_time;
 
 pub struct SoundManager {
     pub transition_sound: Option<Sound>,
     pub bounce_sound: Option<Sound>,
     last_bounce_time: f64,
+    pub muted: bool,
+    /// From 0 (silent) to 1 (as recorded).
+    pub volume: f32,
 }
 
 impl SoundManager {
@@ -19,16 +22,24 @@ impl SoundManager {
             transition_sound: transition,
             bounce_sound: bounce,
             last_bounce_time: 0.,
+            muted: false,
+            volume: 1.,
         }
     }
 
     pub fn play_transition(&self) {
+        if self.muted {
+            return;
+        }
         if let Some(s) = &self.transition_sound {
-            play_sound_once(s);
+            self.play(s);
         }
     }
 
     pub fn play_bounce(&mut self) {
+        if self.muted {
+            return;
+        }
         let now = get_time();
 
         if now - self.last_bounce_time < 0.1 {
@@ -36,8 +47,16 @@ impl SoundManager {
         }
 
         if let Some(s) = &self.bounce_sound {
-            play_sound_once(s);
+            self.play(s);
             self.last_bounce_time = now;
         }
     }
+
+    fn play(&self, sound: &Sound) {
+        let params = PlaySoundParams {
+            looped: false,
+            volume: self.volume,
+        };
+        play_sound(sound, params);
+    }
 }
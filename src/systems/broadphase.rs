@@ -0,0 +1,145 @@
+use std::ops::Range;
+
+use crate::components::Brick;
+use macroquad::prelude::*;
+
+/// Side of one grid cell, in arena units. About a brick wide, so a ball's
+/// path for one step covers only a few cells.
+const CELL: f32 = 64.;
+
+/// Buckets bricks by the grid cells they cover, so a moving ball only has to
+/// check the bricks near its path instead of every brick in the level.
+///
+/// Holds indices into the level's brick list. Bricks outside the arena go in
+/// the nearest edge cell. Dead bricks should be removed and moved bricks
+/// relocated; `rebuild` starts over from the list.
+pub struct BrickGrid {
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl BrickGrid {
+    /// An empty grid covering an arena of the given size.
+    pub fn new(arena: Vec2) -> Self {
+        let cols = (arena.x / CELL).ceil().max(1.) as usize;
+        let rows = (arena.y / CELL).ceil().max(1.) as usize;
+        BrickGrid {
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        }
+    }
+
+    /// Indexes every active brick in `bricks`, forgetting what was there.
+    pub fn rebuild(&mut self, bricks: &[Brick]) {
+        self.cells.iter_mut().for_each(Vec::clear);
+        for (i, brick) in bricks.iter().enumerate() {
+            if brick.active {
+                self.insert(i, brick.bound());
+            }
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, bounds: Rect) {
+        for cell in self.cells_under(bounds) {
+            self.cells[cell].push(index);
+        }
+    }
+
+    pub fn remove(&mut self, index: usize, bounds: Rect) {
+        for cell in self.cells_under(bounds) {
+            self.cells[cell].retain(|&i| i != index);
+        }
+    }
+
+    /// Moves a brick that was at `from` to `to`.
+    pub fn relocate(&mut self, index: usize, from: Rect, to: Rect) {
+        self.remove(index, from);
+        self.insert(index, to);
+    }
+
+    /// Fills `found` with the bricks in the cells `area` touches, each once
+    /// and in list order, so results match a scan of the whole list.
+    pub fn query(&self, area: Rect, found: &mut Vec<usize>) {
+        found.clear();
+        for cell in self.cells_under(area) {
+            found.extend_from_slice(&self.cells[cell]);
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+
+    fn cells_under(&self, area: Rect) -> impl Iterator<Item = usize> + use<> {
+        let cols = span(area.x, area.w, self.cols);
+        let rows = span(area.y, area.h, self.rows);
+        let width = self.cols;
+        rows.flat_map(move |row| cols.clone().map(move |col| row * width + col))
+    }
+}
+
+/// Cells covering `start..start + len` along an axis `count` cells long.
+fn span(start: f32, len: f32, count: usize) -> Range<usize> {
+    let cell = |x: f32| ((x / CELL).floor().max(0.) as usize).min(count - 1);
+    cell(start)..cell(start + len) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BrickKind;
+
+    fn brick(x: f32, y: f32) -> Brick {
+        Brick::new(vec2(x, y), BrickKind::Normal, 1, WHITE)
+    }
+
+    fn query(grid: &BrickGrid, area: Rect) -> Vec<usize> {
+        let mut found = Vec::new();
+        grid.query(area, &mut found);
+        found
+    }
+
+    #[test]
+    fn finds_only_nearby_bricks() {
+        let bricks = [brick(10., 10.), brick(300., 300.), brick(40., 20.)];
+        let mut grid = BrickGrid::new(vec2(800., 600.));
+        grid.rebuild(&bricks);
+
+        assert_eq!(query(&grid, Rect::new(0., 0., 30., 30.)), [0, 2]);
+        assert_eq!(query(&grid, Rect::new(290., 290., 10., 10.)), [1]);
+        assert!(query(&grid, Rect::new(600., 500., 50., 50.)).is_empty());
+    }
+
+    #[test]
+    fn a_brick_across_cells_is_found_once() {
+        let bricks = [brick(CELL - 10., CELL - 10.)];
+        let mut grid = BrickGrid::new(vec2(800., 600.));
+        grid.rebuild(&bricks);
+        assert_eq!(query(&grid, Rect::new(0., 0., 800., 600.)), [0]);
+    }
+
+    #[test]
+    fn removed_and_moved_bricks_are_updated() {
+        let mut bricks = vec![brick(10., 10.), brick(100., 10.)];
+        let mut grid = BrickGrid::new(vec2(800., 600.));
+        grid.rebuild(&bricks);
+
+        grid.remove(0, bricks[0].bound());
+        assert!(query(&grid, Rect::new(0., 0., 30., 30.)).is_empty());
+
+        let from = bricks[1].bound();
+        bricks[1].pos.y = 400.;
+        grid.relocate(1, from, bricks[1].bound());
+        assert!(query(&grid, Rect::new(100., 10., 10., 10.)).is_empty());
+        assert_eq!(query(&grid, Rect::new(100., 400., 10., 10.)), [1]);
+    }
+
+    #[test]
+    fn bricks_outside_the_arena_sit_in_the_edge_cells() {
+        let bricks = [brick(-50., -80.), brick(900., 700.)];
+        let mut grid = BrickGrid::new(vec2(800., 600.));
+        grid.rebuild(&bricks);
+        assert_eq!(query(&grid, Rect::new(0., 0., 1., 1.)), [0]);
+        assert_eq!(query(&grid, Rect::new(790., 590., 1., 1.)), [1]);
+    }
+}
@@ -0,0 +1,233 @@
+use macroquad::prelude::*;
+
+/// Where along a sweep the moving circle first touches something.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Fraction of the motion travelled before contact, from 0 to 1.
+    pub time: f32,
+    /// Unit normal of the touched surface, pointing back at the circle.
+    pub normal: Vec2,
+}
+
+/// Sweeps a circle from `center` along `motion` and returns the earliest
+/// contact with `rect`, if any. Corners are treated as rounded, so hitting one
+/// gives a diagonal normal instead of picking a face.
+///
+/// A circle that already overlaps `rect` hits it at time 0, unless it is
+/// moving away, so a resolved contact doesn't trigger again.
+pub fn sweep_circle_rect(center: Vec2, radius: f32, motion: Vec2, rect: Rect) -> Option<Hit> {
+    if let Some(normal) = overlap_normal(center, radius, rect) {
+        return (motion.dot(normal) < 0.).then_some(Hit { time: 0., normal });
+    }
+
+    // The circle touches the rect when its center enters the rect grown by
+    // the radius, with the corners rounded off.
+    let grown = Rect::new(
+        rect.x - radius,
+        rect.y - radius,
+        rect.w + 2. * radius,
+        rect.h + 2. * radius,
+    );
+    let (time, normal) = ray_rect(center, motion, grown)?;
+    let p = center + motion * time;
+
+    let in_x = p.x >= rect.x && p.x <= rect.x + rect.w;
+    let in_y = p.y >= rect.y && p.y <= rect.y + rect.h;
+    if in_x || in_y {
+        return Some(Hit { time, normal });
+    }
+
+    // Entered the grown rect through a corner square. Missing that corner's
+    // circle means missing the rect altogether.
+    let corner = vec2(
+        if p.x < rect.x {
+            rect.x
+        } else {
+            rect.x + rect.w
+        },
+        if p.y < rect.y {
+            rect.y
+        } else {
+            rect.y + rect.h
+        },
+    );
+    let time = ray_circle(center, motion, corner, radius)?;
+    let normal = (center + motion * time - corner).normalize_or(normal);
+    Some(Hit { time, normal })
+}
+
+/// Push-out normal if the circle overlaps `rect`.
+fn overlap_normal(center: Vec2, radius: f32, rect: Rect) -> Option<Vec2> {
+    let closest = vec2(
+        center.x.clamp(rect.x, rect.x + rect.w),
+        center.y.clamp(rect.y, rect.y + rect.h),
+    );
+    let offset = center - closest;
+    if offset.length_squared() >= radius * radius {
+        return None;
+    }
+    if offset != Vec2::ZERO {
+        return Some(offset.normalize());
+    }
+
+    // Center is inside the rect, push out through the nearest face
+    let faces = [
+        (center.x - rect.x, vec2(-1., 0.)),
+        (rect.x + rect.w - center.x, vec2(1., 0.)),
+        (center.y - rect.y, vec2(0., -1.)),
+        (rect.y + rect.h - center.y, vec2(0., 1.)),
+    ];
+    faces
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, normal)| normal)
+}
+
+/// Slab test for a ray starting outside `rect`. Returns the entry time within
+/// the motion and the normal of the face entered.
+fn ray_rect(origin: Vec2, motion: Vec2, rect: Rect) -> Option<(f32, Vec2)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+
+    let axes = [
+        (origin.x, motion.x, rect.x, rect.x + rect.w, Vec2::X),
+        (origin.y, motion.y, rect.y, rect.y + rect.h, Vec2::Y),
+    ];
+    for (o, m, min, max, axis) in axes {
+        if m == 0. {
+            if o < min || o > max {
+                return None;
+            }
+            continue;
+        }
+        let (t1, t2) = ((min - o) / m, (max - o) / m);
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if near > t_enter {
+            t_enter = near;
+            normal = -axis * m.signum();
+        }
+        t_exit = t_exit.min(far);
+    }
+
+    if t_enter > t_exit || !(0.0..=1.0).contains(&t_enter) {
+        return None;
+    }
+    Some((t_enter, normal))
+}
+
+/// Earliest time within the motion a ray comes within `radius` of `point`.
+fn ray_circle(origin: Vec2, motion: Vec2, point: Vec2, radius: f32) -> Option<f32> {
+    let d = origin - point;
+    let a = motion.dot(motion);
+    if a == 0. {
+        return None;
+    }
+    let b = 2. * d.dot(motion);
+    let c = d.dot(d) - radius * radius;
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / (2. * a);
+    (0.0..=1.0).contains(&t).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BRICK: Rect = Rect {
+        x: 100.,
+        y: 100.,
+        w: 60.,
+        h: 20.,
+    };
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn face_hit_from_below() {
+        let hit = sweep_circle_rect(vec2(130., 160.), 10., vec2(0., -40.), BRICK).unwrap();
+        // Bottom face at y = 120, touched when the center reaches y = 130
+        assert_near(hit.time, 0.75);
+        assert_eq!(hit.normal, vec2(0., 1.));
+    }
+
+    #[test]
+    fn face_hit_from_the_side() {
+        let hit = sweep_circle_rect(vec2(50., 110.), 10., vec2(80., 0.), BRICK).unwrap();
+        assert_near(hit.time, 0.5);
+        assert_eq!(hit.normal, vec2(-1., 0.));
+    }
+
+    #[test]
+    fn corner_hit_reflects_diagonally() {
+        // Heading straight at the top-left corner along the diagonal
+        let start = vec2(100., 100.) - Vec2::splat(30.);
+        let hit = sweep_circle_rect(start, 10., Vec2::splat(30.), BRICK).unwrap();
+
+        let expected = Vec2::splat(-1.).normalize();
+        assert_near(hit.normal.x, expected.x);
+        assert_near(hit.normal.y, expected.y);
+        // Contact when the center is one radius away from the corner
+        let contact = start + Vec2::splat(30.) * hit.time;
+        assert_near(contact.distance(vec2(100., 100.)), 10.);
+    }
+
+    #[test]
+    fn grazing_past_a_corner_misses() {
+        // Passes the top-left corner with 2px to spare
+        let start = vec2(40., 88.);
+        let motion = vec2(200., 0.);
+        assert!(sweep_circle_rect(start, 10., motion, BRICK).is_none());
+    }
+
+    #[test]
+    fn clipping_a_corner_hits_it_not_the_face() {
+        // Center passes 5px above the top edge, level with the top-left corner
+        let hit = sweep_circle_rect(vec2(40., 95.), 10., vec2(100., 0.), BRICK).unwrap();
+        assert!(hit.normal.x < 0. && hit.normal.y < 0.);
+        let contact = vec2(40., 95.) + vec2(100., 0.) * hit.time;
+        assert_near(contact.distance(vec2(100., 100.)), 10.);
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel() {
+        // 700px/s with a 0.25s hitch is 175px, far more than the brick height
+        let hit = sweep_circle_rect(vec2(130., 250.), 15., vec2(0., -175.), BRICK).unwrap();
+        assert_eq!(hit.normal, vec2(0., 1.));
+        assert_near(250. - 175. * hit.time, 135.);
+    }
+
+    #[test]
+    fn fast_diagonal_ball_hits_the_first_face_it_reaches() {
+        // Level with the left side while still below the brick, so it's the
+        // bottom face that gets hit
+        let motion = vec2(300., -300.);
+        let hit = sweep_circle_rect(vec2(20., 260.), 15., motion, BRICK).unwrap();
+        let contact = vec2(20., 260.) + motion * hit.time;
+        assert_eq!(hit.normal, vec2(0., 1.));
+        assert_near(contact.y, 135.);
+        assert_near(contact.x, 145.);
+    }
+
+    #[test]
+    fn overlapping_ball_moving_in_hits_immediately() {
+        let hit = sweep_circle_rect(vec2(130., 125.), 10., vec2(0., -5.), BRICK).unwrap();
+        assert_eq!(hit.time, 0.);
+        assert_eq!(hit.normal, vec2(0., 1.));
+    }
+
+    #[test]
+    fn overlapping_ball_moving_out_is_ignored() {
+        assert!(sweep_circle_rect(vec2(130., 125.), 10., vec2(0., 5.), BRICK).is_none());
+    }
+
+    #[test]
+    fn short_motion_stops_before_contact() {
+        assert!(sweep_circle_rect(vec2(130., 160.), 10., vec2(0., -20.), BRICK).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::components::bricks::ROW_PITCH;
+use crate::components::{Ball, Brick, Status};
+use crate::levels::draft::COLS;
+use crate::levels::parser::Cell;
+use macroquad::prelude::*;
+
+/// Rows on the field when an endless run starts.
+pub const START_ROWS: usize = 4;
+/// Points for each row that comes in without reaching the paddle.
+pub const ROW_POINTS: u32 = 100;
+/// Seconds before the first new row.
+const FIRST_INTERVAL: f32 = 12.;
+/// Each row comes this much sooner than the one before...
+const INTERVAL_SHRINK: f32 = 0.94;
+/// ...down to this.
+const MIN_INTERVAL: f32 = 3.;
+
+/// Endless mode's clock: new rows come in at the top at shortening intervals,
+/// pushing the field down towards the paddle.
+#[derive(Clone, Debug)]
+pub struct Descent {
+    /// Rows that have come in since the run started, not counting the ones
+    /// it started with.
+    pub rows: u32,
+    /// Seconds until the next row.
+    pub timer: f32,
+    /// Seconds between rows, shrinking with each one.
+    pub interval: f32,
+}
+
+impl Default for Descent {
+    fn default() -> Self {
+        Descent {
+            rows: 0,
+            timer: FIRST_INTERVAL,
+            interval: FIRST_INTERVAL,
+        }
+    }
+}
+
+impl Descent {
+    /// Runs the clock. Returns true once the next row is due.
+    pub fn advance(&mut self, dt: f32) -> bool {
+        self.timer -= dt;
+        self.timer <= 0.
+    }
+
+    /// Counts a row in, due or early, and winds the clock for the next one.
+    pub fn next_row(&mut self) {
+        self.rows += 1;
+        self.interval = (self.interval * INTERVAL_SHRINK).max(MIN_INTERVAL);
+        self.timer = self.interval;
+    }
+
+    /// Share of the wait for the next row gone by, 0 to 1.
+    pub fn progress(&self) -> f32 {
+        1. - (self.timer / self.interval).clamp(0., 1.)
+    }
+}
+
+/// Moves every live brick down a row and puts `row` in at the top. Destroyed
+/// bricks are dropped from the list.
+pub fn push_row(bricks: &mut Vec<Brick>, row: &[Option<Cell>], arena_width: f32) {
+    bricks.retain(|b| b.active);
+    for brick in bricks.iter_mut() {
+        brick.pos.y += ROW_PITCH;
+        brick.row += 1;
+    }
+    for (col, cell) in row.iter().enumerate() {
+        if let Some(cell) = cell {
+            let pos = Brick::grid_pos(0, col, COLS, arena_width);
+            bricks.push(Brick::new(pos, cell.kind, cell.hp, cell.color));
+        }
+    }
+}
+
+/// Pushes balls in flight out from under bricks that moved onto them,
+/// heading down, as if the row shoved them.
+pub fn shove_balls(balls: &mut [Ball], bricks: &[Brick]) {
+    for ball in balls.iter_mut().filter(|b| b.status == Status::Running) {
+        // Pushing a ball out of one brick can land it in the one below
+        while let Some(brick) = bricks.iter().find(|b| b.active && touches(ball, b.bound())) {
+            ball.pos.y = brick.pos.y + brick.height + ball.radius;
+            ball.velocity.y = ball.velocity.y.abs();
+        }
+        ball.prev_pos = ball.pos;
+    }
+}
+
+fn touches(ball: &Ball, rect: Rect) -> bool {
+    let nearest = ball.pos.clamp(rect.point(), rect.point() + rect.size());
+    ball.pos.distance_squared(nearest) < ball.radius * ball.radius
+}
+
+/// Whether a live brick has come down to `line`, the top of the paddle.
+pub fn reached(bricks: &[Brick], line: f32) -> bool {
+    bricks
+        .iter()
+        .any(|b| b.active && b.is_breakable() && b.pos.y + b.height >= line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::BrickKind;
+
+    fn cell() -> Option<Cell> {
+        Some(Cell {
+            kind: BrickKind::Normal,
+            hp: 1,
+            color: WHITE,
+        })
+    }
+
+    #[test]
+    fn rows_come_sooner_and_sooner() {
+        let mut descent = Descent::default();
+        let mut waits = Vec::new();
+        for _ in 0..60 {
+            let mut waited = 0;
+            while !descent.advance(0.25) {
+                waited += 1;
+            }
+            descent.next_row();
+            waits.push(waited);
+        }
+        assert!(waits.windows(2).all(|w| w[1] <= w[0]));
+        assert!(waits[1] < waits[0]);
+        assert_eq!(*waits.last().unwrap() + 1, (MIN_INTERVAL / 0.25) as i32);
+        assert_eq!(descent.rows, 60);
+    }
+
+    #[test]
+    fn a_new_row_pushes_the_field_down() {
+        let mut bricks = Vec::new();
+        push_row(&mut bricks, &[cell(), None, cell()], 800.);
+        bricks[0].die();
+        push_row(&mut bricks, &[None, cell()], 800.);
+
+        assert_eq!(bricks.len(), 2);
+        assert_eq!(bricks[0].row, 1);
+        assert_eq!(bricks[0].pos, Brick::grid_pos(1, 2, COLS, 800.));
+        assert_eq!(bricks[1].row, 0);
+        assert_eq!(bricks[1].pos, Brick::grid_pos(0, 1, COLS, 800.));
+
+        let line = bricks[0].pos.y + bricks[0].height;
+        assert!(reached(&bricks, line));
+        assert!(!reached(&bricks, line + 1.));
+    }
+
+    #[test]
+    fn a_ball_under_a_new_row_is_shoved_below_it() {
+        let mut bricks = Vec::new();
+        push_row(&mut bricks, &[cell()], 800.);
+        push_row(&mut bricks, &[cell()], 800.);
+        let mut ball = Ball::new();
+        ball.status = Status::Running;
+        ball.pos = bricks[1].center();
+        ball.velocity = vec2(100., -300.);
+
+        shove_balls(std::slice::from_mut(&mut ball), &bricks);
+        assert!(bricks.iter().all(|b| !touches(&ball, b.bound())));
+        assert!(ball.pos.y > bricks[0].pos.y);
+        assert_eq!(ball.velocity, vec2(100., 300.));
+    }
+}
@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+
+/// Entries kept per game mode.
+pub const TABLE_SIZE: usize = 10;
+pub const MAX_NAME_LEN: usize = 10;
+
+const HEADER: &str = "# rayball high scores v1";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameMode {
+    Campaign,
+    Endless,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Campaign, GameMode::Endless];
+
+    /// Name used for the mode in the high-score file.
+    fn key(self) -> &'static str {
+        match self {
+            GameMode::Campaign => "campaign",
+            GameMode::Endless => "endless",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<GameMode> {
+        GameMode::ALL.into_iter().find(|m| m.key() == key)
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            GameMode::Campaign => "CAMPAIGN",
+            GameMode::Endless => "ENDLESS",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    pub score: u32,
+}
+
+/// Top scores for every game mode, saved to the platform data directory on
+/// native builds and kept in memory only on the web.
+pub struct HighScores {
+    path: Option<PathBuf>,
+    tables: Vec<(GameMode, Vec<Entry>)>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        let mut scores = HighScores::empty(scores_path());
+        let Some(path) = scores.path.clone() else {
+            return scores;
+        };
+        let src = match std::fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return scores,
+            Err(e) => {
+                log::warn!("Could not read high scores from {}: {e}", path.display());
+                return scores;
+            }
+        };
+
+        match scores.read(&src) {
+            Ok(()) => log::info!("Loaded high scores from {}", path.display()),
+            Err(e) => {
+                log::warn!("Ignoring corrupted high score file {}: {e}", path.display());
+            }
+        }
+        scores
+    }
+
+    fn empty(path: Option<PathBuf>) -> Self {
+        HighScores {
+            path,
+            tables: GameMode::ALL.iter().map(|&m| (m, Vec::new())).collect(),
+        }
+    }
+
+    /// Adds the entries saved in `src`. Nothing is added if any of it is
+    /// corrupted.
+    fn read(&mut self, src: &str) -> Result<(), String> {
+        for (mode, entry) in parse(src)? {
+            self.push_sorted(mode, entry);
+        }
+        Ok(())
+    }
+
+    pub fn table(&self, mode: GameMode) -> &[Entry] {
+        self.tables
+            .iter()
+            .find(|(m, _)| *m == mode)
+            .map(|(_, t)| t.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Whether `score` would make it onto the table for `mode`.
+    pub fn qualifies(&self, mode: GameMode, score: u32) -> bool {
+        let table = self.table(mode);
+        score > 0 && (table.len() < TABLE_SIZE || table.iter().any(|e| score > e.score))
+    }
+
+    /// Adds an entry and saves the table. Returns its rank, starting at 0, if
+    /// it made the cut.
+    pub fn insert(&mut self, mode: GameMode, name: &str, score: u32) -> Option<usize> {
+        let entry = Entry {
+            name: sanitize_name(name),
+            score,
+        };
+        let rank = self.push_sorted(mode, entry);
+        if rank.is_some() {
+            self.save();
+        }
+        rank
+    }
+
+    fn push_sorted(&mut self, mode: GameMode, entry: Entry) -> Option<usize> {
+        let table = match self.tables.iter_mut().find(|(m, _)| *m == mode) {
+            Some((_, table)) => table,
+            None => {
+                self.tables.push((mode, Vec::new()));
+                &mut self.tables.last_mut().unwrap().1
+            }
+        };
+        // Ties go below the existing entries
+        let rank = table.partition_point(|e| e.score >= entry.score);
+        if rank >= TABLE_SIZE {
+            return None;
+        }
+        table.insert(rank, entry);
+        table.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        if let Some(dir) = path.parent()
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            log::warn!("Could not create {}: {e}", dir.display());
+            return;
+        }
+        match std::fs::write(path, self.to_text()) {
+            Ok(()) => log::info!("Saved high scores to {}", path.display()),
+            Err(e) => log::warn!("Could not save high scores to {}: {e}", path.display()),
+        }
+    }
+
+    /// The tables in the high-score file format.
+    fn to_text(&self) -> String {
+        let mut out = String::from(HEADER);
+        out.push('\n');
+        for (mode, table) in &self.tables {
+            for entry in table {
+                out.push_str(&format!(
+                    "{}\t{}\t{}\n",
+                    mode.key(),
+                    entry.name,
+                    entry.score
+                ));
+            }
+        }
+        out
+    }
+}
+
+/// Keeps names printable and short enough for the table, and free of the
+/// tabs and newlines the file format uses as separators.
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LEN)
+        .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        "???".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn parse(src: &str) -> Result<Vec<(GameMode, Entry)>, String> {
+    let mut lines = src.lines();
+    if lines.next().map(str::trim) != Some(HEADER) {
+        return Err("missing or unknown header".to_owned());
+    }
+
+    let mut entries = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line_no = i + 2;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [mode, name, score] = fields[..] else {
+            return Err(format!("line {line_no}: expected 3 tab-separated fields"));
+        };
+        let mode =
+            GameMode::from_key(mode).ok_or(format!("line {line_no}: unknown mode `{mode}`"))?;
+        let score = score
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {line_no}: invalid score `{score}`"))?;
+        entries.push((
+            mode,
+            Entry {
+                name: sanitize_name(name),
+                score,
+            },
+        ));
+    }
+    Ok(entries)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn scores_path() -> Option<PathBuf> {
+    let path = dirs::data_dir().map(|d| d.join("rayball").join("highscores.txt"));
+    if path.is_none() {
+        log::warn!("No data directory found, high scores won't be saved");
+    }
+    path
+}
+
+#[cfg(target_arch = "wasm32")]
+fn scores_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(scores: &HighScores, mode: GameMode) -> Vec<&str> {
+        scores.table(mode).iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn corrupted_files_leave_the_tables_empty() {
+        for src in [
+            "",
+            "garbage",
+            "# rayball high scores v2\ncampaign\tANN\t100\n",
+            "# rayball high scores v1\ncampaign\tANN\t100\ncampaign\tBOB\n",
+            "# rayball high scores v1\ncampaign\tANN\t100\ncampaign\tBOB\t1e3\n",
+            "# rayball high scores v1\ncampaign\tANN\t100\nversus\tBOB\t50\n",
+        ] {
+            let mut scores = HighScores::empty(None);
+            assert!(scores.read(src).is_err(), "{src:?} should not load");
+            for mode in GameMode::ALL {
+                assert!(scores.table(mode).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn saved_tables_load_back() {
+        let mut scores = HighScores::empty(None);
+        scores.insert(GameMode::Campaign, "ann", 300);
+        scores.insert(GameMode::Campaign, "bob", 500);
+        scores.insert(GameMode::Endless, "cy\td", 200);
+
+        let mut loaded = HighScores::empty(None);
+        loaded.read(&scores.to_text()).unwrap();
+        assert_eq!(loaded.to_text(), scores.to_text());
+        assert_eq!(names(&loaded, GameMode::Campaign), ["bob", "ann"]);
+        assert_eq!(names(&loaded, GameMode::Endless), ["cyd"]);
+        assert_eq!(loaded.table(GameMode::Endless)[0].score, 200);
+    }
+
+    #[test]
+    fn ties_go_below_earlier_entries() {
+        let mut scores = HighScores::empty(None);
+        assert_eq!(scores.insert(GameMode::Campaign, "ann", 100), Some(0));
+        assert_eq!(scores.insert(GameMode::Campaign, "bob", 300), Some(0));
+        assert_eq!(scores.insert(GameMode::Campaign, "cy", 100), Some(2));
+        assert_eq!(scores.insert(GameMode::Campaign, "dee", 200), Some(1));
+        assert_eq!(
+            names(&scores, GameMode::Campaign),
+            ["bob", "dee", "ann", "cy"]
+        );
+        assert!(scores.table(GameMode::Endless).is_empty());
+    }
+
+    #[test]
+    fn only_the_top_entries_are_kept() {
+        let mut scores = HighScores::empty(None);
+        for score in 1..=TABLE_SIZE as u32 + 5 {
+            scores.insert(GameMode::Endless, "ann", score * 10);
+        }
+        let table = scores.table(GameMode::Endless);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[0].score, (TABLE_SIZE as u32 + 5) * 10);
+        assert_eq!(table[TABLE_SIZE - 1].score, 60);
+    }
+
+    #[test]
+    fn a_full_table_needs_a_better_score() {
+        let mut scores = HighScores::empty(None);
+        assert!(!scores.qualifies(GameMode::Campaign, 0));
+        for score in 1..=TABLE_SIZE as u32 {
+            assert!(scores.qualifies(GameMode::Campaign, 1));
+            scores.insert(GameMode::Campaign, "ann", score * 10);
+        }
+        // Tenth place has 10 points
+        assert!(!scores.qualifies(GameMode::Campaign, 5));
+        assert!(!scores.qualifies(GameMode::Campaign, 10));
+        assert!(scores.qualifies(GameMode::Campaign, 11));
+        assert_eq!(scores.insert(GameMode::Campaign, "bob", 10), None);
+        assert_eq!(scores.insert(GameMode::Campaign, "bob", 11), Some(9));
+        assert_eq!(scores.table(GameMode::Campaign)[9].name, "bob");
+    }
+}
@@ -1,3 +1,8 @@
 pub mod audio;
+pub mod broadphase;
+pub mod collision;
+pub mod descent;
+pub mod highscore;
 pub mod physics;
 pub mod render;
+pub mod view;
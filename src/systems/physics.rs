@@ -1,113 +1,366 @@
-use crate::components::{Ball, Brick, Platform,particle};
-use crate::constants::VELOCITY;
-use crate::game::GameEvent;
+use crate::components::bricks::EXPLOSION_RADIUS;
+use crate::components::{Ball, Brick, BrickKind, Platform, Status, particle};
+use crate::constants::{CURVE, ENGLISH, PLATFORM_SPEED, SPIN_FADE, VELOCITY};
+use crate::systems::broadphase::BrickGrid;
+use crate::systems::collision::{Hit, sweep_circle_rect};
+use crate::world::GameEvent;
 use macroquad::prelude::*;
 
-pub fn resolve_ball_collisions(
+/// Most surfaces a ball can bounce off in one step; any motion left after
+/// that is dropped.
+const MAX_BOUNCES: usize = 8;
+/// Gap left between the ball and a surface it bounced off, so the next sweep
+/// starts clear of it.
+const CONTACT_GAP: f32 = 0.01;
+/// Fastest a ball leaves the paddle sideways, aim and english together.
+const MAX_SIDE_SPEED: f32 = VELOCITY * 1.25;
+/// Bricks the ball reaches within this distance of the first one it touches
+/// are hit together, as one surface.
+const SAME_CONTACT: f32 = 0.05;
+
+/// How fast balls fly this step and how flat they may go.
+#[derive(Clone, Copy)]
+pub struct Flight {
+    pub speed: f32,
+    /// Smallest share of its speed a ball must move up or down with.
+    pub min_rise: f32,
+}
+
+enum Obstacle {
+    Wall,
+    Platform,
+    /// Every brick touched at once, each with the normal of its own contact.
+    Bricks(Vec<(usize, Vec2)>),
+}
+
+/// Moves a running ball through one step of `dt` seconds. Every contact along
+/// the way is found by sweeping, so fast balls bounce instead of tunneling,
+/// and whatever motion is left after a bounce carries on in the new direction.
+pub fn move_ball(
     ball: &mut Ball,
     platform: &Platform,
     bricks: &mut [Brick],
+    grid: &mut BrickGrid,
+    arena: Vec2,
+    flight: Flight,
+    dt: f32,
 ) -> Vec<GameEvent> {
     let mut events = vec![];
-    if let Some(e) = handle_brick_collisions(bricks, ball) {
-        events.push(e);
+    let mut time_left = dt;
+    apply_spin(ball, dt);
+    steer(ball, flight);
+
+    for _ in 0..MAX_BOUNCES {
+        if ball.status != Status::Running || time_left <= 0. {
+            break;
+        }
+        let motion = ball.velocity * time_left;
+        let Some((hit, obstacle)) = earliest_hit(ball, motion, arena, platform, bricks, grid)
+        else {
+            ball.pos += motion;
+            break;
+        };
+
+        ball.pos += motion * hit.time + hit.normal * CONTACT_GAP;
+        time_left *= 1. - hit.time;
+        match obstacle {
+            Obstacle::Wall => {
+                reflect(ball, hit.normal);
+                events.push(GameEvent::BallHitWall);
+            }
+            Obstacle::Platform => events.push(handle_platform_hit(ball, platform, hit)),
+            Obstacle::Bricks(hits) => {
+                events.extend(handle_brick_hits(bricks, grid, &hits, ball, hit))
+            }
+        }
+        steer(ball, flight);
     }
-    if let Some(e) = handle_platform_collisions(ball, platform) {
+
+    if let Some(e) = handle_drop(ball, arena.y) {
         events.push(e);
     }
     events
 }
 
-pub fn update_ball_position(ball: &mut Ball, dt: f32) -> Vec<GameEvent> {
-    ball.pos += ball.velocity * dt;
-    let mut events = vec![];
-    if let Some(e) = handle_wall_collisions(ball) {
-        events.push(e);
+/// Bends the ball's path by its spin, which wears off at a steady rate.
+fn apply_spin(ball: &mut Ball, dt: f32) {
+    if ball.spin == 0. {
+        return;
     }
-    events
+    ball.velocity.x += ball.spin * dt;
+    let fade = SPIN_FADE * dt;
+    ball.spin = if ball.spin.abs() <= fade {
+        0.
+    } else {
+        ball.spin - fade * ball.spin.signum()
+    };
+}
+
+/// Brings the ball to the flight speed, tipping it up or down if it travels
+/// flatter than allowed so it can't rally between the side walls forever.
+fn steer(ball: &mut Ball, flight: Flight) {
+    let Some(mut dir) = ball.velocity.try_normalize() else {
+        return;
+    };
+    if dir.y.abs() < flight.min_rise {
+        // Level flight is tipped upwards, away from the paddle
+        let y = if dir.y > 0. {
+            flight.min_rise
+        } else {
+            -flight.min_rise
+        };
+        dir = vec2((1. - y * y).sqrt().copysign(dir.x), y);
+    }
+    ball.velocity = dir * flight.speed;
 }
 
 pub fn snap_ball_to_platform(ball: &mut Ball, platform: &Platform) {
-    ball.pos.x = center_x(platform.bounds());
+    let bounds = platform.bounds();
+    // The paddle may have shrunk since the ball was caught
+    let offset = ball.hold_offset.clamp(-bounds.w / 2., bounds.w / 2.);
+    ball.pos.x = center_x(bounds) + offset;
     ball.pos.y = platform.pos.y - ball.radius;
 }
 
-fn handle_platform_collisions(ball: &mut Ball, platform: &Platform) -> Option<GameEvent> {
+fn earliest_hit(
+    ball: &Ball,
+    motion: Vec2,
+    arena: Vec2,
+    platform: &Platform,
+    bricks: &[Brick],
+    grid: &BrickGrid,
+) -> Option<(Hit, Obstacle)> {
+    let sweep = |rect| sweep_circle_rect(ball.pos, ball.radius, motion, rect);
+
+    let walls = sweep_walls(ball, motion, arena).map(|h| (h, Obstacle::Wall));
+    let paddle = sweep(platform.bounds()).map(|h| (h, Obstacle::Platform));
+
+    let mut nearby = Vec::new();
+    grid.query(swept_area(ball, motion), &mut nearby);
+    let brick_hits = sweep_bricks(ball, motion, bricks, nearby);
+    let bricks = first_bricks(&brick_hits, motion);
+
+    walls
+        .into_iter()
+        .chain(paddle)
+        .chain(bricks)
+        .min_by(|a, b| a.0.time.total_cmp(&b.0.time))
+}
+
+/// Sweeps the ball against the live bricks among `candidates`, indices into
+/// `bricks`, returning each one it would touch moving by `motion`.
+pub(crate) fn sweep_bricks(
+    ball: &Ball,
+    motion: Vec2,
+    bricks: &[Brick],
+    candidates: impl IntoIterator<Item = usize>,
+) -> Vec<(usize, Hit)> {
+    candidates
+        .into_iter()
+        .filter(|&i| bricks[i].active)
+        .filter_map(|i| {
+            sweep_circle_rect(ball.pos, ball.radius, motion, bricks[i].bound()).map(|h| (i, h))
+        })
+        .collect()
+}
+
+/// Everything the ball covers moving by `motion`.
+pub(crate) fn swept_area(ball: &Ball, motion: Vec2) -> Rect {
+    let r = Vec2::splat(ball.radius);
+    let min = ball.pos.min(ball.pos + motion) - r;
+    let max = ball.pos.max(ball.pos + motion) + r;
+    Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+/// Groups the bricks touched first into one hit. Its normal is the sum of
+/// theirs, so a ball landing on the seam between two bricks bounces as if off
+/// one flat wall.
+fn first_bricks(hits: &[(usize, Hit)], motion: Vec2) -> Option<(Hit, Obstacle)> {
+    let first = hits.iter().map(|(_, h)| h.time).min_by(f32::total_cmp)?;
+    let same_time = SAME_CONTACT / motion.length().max(f32::EPSILON);
+    let touched: Vec<(usize, Vec2)> = hits
+        .iter()
+        .filter(|(_, h)| h.time <= first + same_time)
+        .map(|(i, h)| (*i, h.normal))
+        .collect();
+    // Normals that cancel out, like being pinched between two bricks, leave
+    // the first one to bounce off
+    let normal = touched
+        .iter()
+        .map(|(_, n)| *n)
+        .sum::<Vec2>()
+        .try_normalize()
+        .unwrap_or(touched[0].1);
+    let hit = Hit {
+        time: first,
+        normal,
+    };
+    Some((hit, Obstacle::Bricks(touched)))
+}
+
+/// The left, right and top edges of the arena. The bottom is open.
+fn sweep_walls(ball: &Ball, motion: Vec2, arena: Vec2) -> Option<Hit> {
+    let r = ball.radius;
+    let walls = [
+        (motion.x < 0., (r - ball.pos.x) / motion.x, Vec2::X),
+        (
+            motion.x > 0.,
+            (arena.x - r - ball.pos.x) / motion.x,
+            -Vec2::X,
+        ),
+        (motion.y < 0., (r - ball.pos.y) / motion.y, Vec2::Y),
+    ];
+    walls
+        .into_iter()
+        .filter(|(approaching, time, _)| *approaching && *time <= 1.)
+        .map(|(_, time, normal)| Hit {
+            // Already past the wall counts as touching it right away
+            time: time.max(0.),
+            normal,
+        })
+        .min_by(|a, b| a.time.total_cmp(&b.time))
+}
+
+fn reflect(ball: &mut Ball, normal: Vec2) {
+    ball.velocity -= 2. * ball.velocity.dot(normal) * normal;
+}
+
+fn handle_platform_hit(ball: &mut Ball, platform: &Platform, hit: Hit) -> GameEvent {
     let p_bound = platform.bounds();
+    let hit_point = vec2(ball.pos.x, platform.pos.y);
 
-    if circle_rect_collision(ball.pos, ball.radius, p_bound) && ball.velocity.y > 0.0 {
-        ball.velocity.y *= -1.0;
+    // Only the top of the paddle steers the ball, its ends just bounce it
+    if hit.normal.y >= 0. {
+        reflect(ball, hit.normal);
+        return GameEvent::BallHitPlatform(hit_point);
+    }
+
+    if platform.sticky {
+        ball.hold_offset = ball.pos.x - center_x(p_bound);
         ball.pos.y = platform.pos.y - ball.radius;
+        ball.velocity = Vec2::ZERO;
+        ball.spin = 0.;
+        ball.status = Status::Start;
+        return GameEvent::BallHitPlatform(hit_point);
+    }
 
-        let diff = ball.pos.x - center_x(p_bound);
-        ball.velocity.x = (diff / (p_bound.w / 2.0)) * VELOCITY;
-        let hit_point = vec2(ball.pos.x, platform.pos.y);
+    // Only the direction is set here, move_ball brings it up to speed
+    ball.velocity.y = -VELOCITY;
 
-        return Some(GameEvent::BallHitPlatform(hit_point));
-    }
-    None
+    let diff = ball.pos.x - center_x(p_bound);
+    let aim = (diff / (p_bound.w / 2.0)).clamp(-1., 1.) * VELOCITY;
+    // A moving paddle drags the ball along and sets it spinning. Mouse moves
+    // can jump further in a tick than the keys ever do, so they count as
+    // moving no faster than the keys.
+    let paddle = platform.velocity.clamp(-PLATFORM_SPEED, PLATFORM_SPEED);
+    ball.velocity.x = (aim + paddle * ENGLISH).clamp(-MAX_SIDE_SPEED, MAX_SIDE_SPEED);
+    ball.spin = paddle * CURVE;
+
+    GameEvent::BallHitPlatform(hit_point)
 }
 
-fn handle_brick_collisions(bricks: &mut [Brick], ball: &mut Ball) -> Option<GameEvent> {
-    for brick in bricks.iter_mut().filter(|b| b.active) {
-        let bound = brick.bound();
-        if circle_rect_collision(ball.pos, ball.radius, bound) {
-            let hitting_from_below = ball.velocity.y < 0.0 && ball.pos.y > bound.y + bound.h;
-            // let _hitting_from_above = ball.velocity.y > 0.0 && ball.pos.y < bound.y;
+fn handle_brick_hits(
+    bricks: &mut [Brick],
+    grid: &mut BrickGrid,
+    hits: &[(usize, Vec2)],
+    ball: &mut Ball,
+    hit: Hit,
+) -> Vec<GameEvent> {
+    // Piercing balls carry straight on unless something they can't break
+    // is in the way
+    let piercing = ball.piercing && hits.iter().all(|&(i, _)| bricks[i].is_breakable());
+    if !piercing {
+        reflect(ball, hit.normal);
+    }
 
-            let hitting_from_left = ball.velocity.x > 0.0 && ball.pos.x < bound.x;
-            let hitting_from_right = ball.velocity.x < 0.0 && ball.pos.x > bound.x + bound.w;
+    let mut events = vec![];
+    for &(i, normal) in hits {
+        // Already blown up by another brick hit this step
+        if !bricks[i].active {
+            continue;
+        }
+        events.push(GameEvent::BrickCollision(
+            ball.pos,
+            side_hit(normal),
+            bricks[i].row,
+        ));
 
-            if hitting_from_left || hitting_from_right {
-                ball.velocity.x *= -1.0;
-            } else {
-                ball.velocity.y *= -1.0;
+        let destroyed = if piercing {
+            bricks[i].die();
+            true
+        } else {
+            bricks[i].hit()
+        };
+        if destroyed {
+            grid.remove(i, bricks[i].bound());
+            events.push(GameEvent::BrickDestroyed(
+                bricks[i].center(),
+                bricks[i].points(),
+            ));
+            if bricks[i].kind == BrickKind::Explosive {
+                events.extend(explode(bricks, grid, i));
             }
-
-            brick.die();
-            
-            let direction = if hitting_from_left {
-                particle::Direction::Left
-            } else if hitting_from_right {
-                particle::Direction::Right
-            } else if hitting_from_below {
-                particle::Direction::Down
-            } else {
-                particle::Direction::Up
-            };
-            return Some(GameEvent::BrickCollision(ball.pos, direction));
         }
     }
-    None
+    events
 }
 
-fn handle_wall_collisions(ball: &mut Ball) -> Option<GameEvent> {
-    let touched_down = (ball.pos.y + ball.radius >= screen_height()) && (ball.velocity.y > 0.0);
-    if touched_down {
-        ball.pos.y = screen_height() - ball.radius;
-        ball.die();
-        return Some(GameEvent::BallDropped);
+/// Which side of a brick was hit. The normal points back at the ball, so it
+/// says which side that was.
+fn side_hit(normal: Vec2) -> particle::Direction {
+    if normal.x.abs() > normal.y.abs() {
+        if normal.x < 0. {
+            particle::Direction::Left
+        } else {
+            particle::Direction::Right
+        }
+    } else if normal.y > 0. {
+        particle::Direction::Down
+    } else {
+        particle::Direction::Up
     }
+}
 
-    let mut hit_wall = false;
-    if ball.pos.y < ball.radius && ball.velocity.y < 0.0 {
-        ball.pos.y = ball.radius;
-        ball.velocity.y *= -1.0;
-        hit_wall = true;
-    }
-    if ball.pos.x + ball.radius >= screen_width() && ball.velocity.x > 0.0 {
-        ball.pos.x = screen_width() - ball.radius;
-        ball.velocity.x *= -1.0;
-        hit_wall = true;
-    }
-    if ball.pos.x < ball.radius && ball.velocity.x < 0.0 {
-        ball.pos.x = ball.radius;
-        ball.velocity.x *= -1.0;
-        hit_wall = true;
+/// Destroys every breakable brick around an exploding one. Explosives caught
+/// in the blast go off too, so a cluster of them chain-reacts.
+fn explode(bricks: &mut [Brick], grid: &mut BrickGrid, origin: usize) -> Vec<GameEvent> {
+    let mut events = vec![];
+    let mut pending = vec![origin];
+    let mut nearby = Vec::new();
+
+    while let Some(i) = pending.pop() {
+        let center = bricks[i].center();
+        events.push(GameEvent::BrickExploded(center));
+
+        let blast = Rect::new(
+            center.x - EXPLOSION_RADIUS,
+            center.y - EXPLOSION_RADIUS,
+            EXPLOSION_RADIUS * 2.,
+            EXPLOSION_RADIUS * 2.,
+        );
+        grid.query(blast, &mut nearby);
+        for &j in &nearby {
+            let brick = &mut bricks[j];
+            let in_blast = brick.center().distance(center) <= EXPLOSION_RADIUS;
+            if brick.active && brick.is_breakable() && in_blast {
+                brick.die();
+                grid.remove(j, brick.bound());
+                events.push(GameEvent::BrickDestroyed(brick.center(), brick.points()));
+                if brick.kind == BrickKind::Explosive {
+                    pending.push(j);
+                }
+            }
+        }
     }
+    events
+}
 
-    if hit_wall {
-        return Some(GameEvent::BallHitWall);
+fn handle_drop(ball: &mut Ball, floor: f32) -> Option<GameEvent> {
+    let touched_down = (ball.pos.y + ball.radius >= floor) && (ball.velocity.y > 0.0);
+    if touched_down {
+        ball.pos.y = floor - ball.radius;
+        ball.die();
+        return Some(GameEvent::BallDropped(ball.pos));
     }
     None
 }
@@ -124,14 +377,3 @@ pub fn transition_ball(ball: &mut Ball, platform: &Platform, dt: f32) -> bool {
     }
     false
 }
-
-// Helper function because Macroquad doesn't have a built-in Circle-to-Rect check
-fn circle_rect_collision(center: Vec2, radius: f32, rect: Rect) -> bool {
-    let closest_x = center.x.clamp(rect.x, rect.x + rect.w);
-    let closest_y = center.y.clamp(rect.y, rect.y + rect.h);
-
-    let distance_x = center.x - closest_x;
-    let distance_y = center.y - closest_y;
-
-    (distance_x * distance_x) + (distance_y * distance_y) < (radius * radius)
-}
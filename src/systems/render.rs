@@ -1,84 +1,332 @@
-use crate::{DEFAULT_FONT, components::*, constants::BALL_RADIUS};
+use crate::components::*;
+use crate::constants::{ARENA, BALL_RADIUS, BG_COLOR};
+use crate::systems::descent::Descent;
+use crate::systems::highscore::Entry;
+use crate::systems::view::View;
 use macroquad::prelude::*;
+use std::sync::OnceLock;
 
-pub fn draw_game_ui(lives: u8, ball_status: &Status, dead_balls_pos: &Vec<Vec2>, won: bool) {
+/// Font every text is drawn with, loaded once at startup.
+pub static DEFAULT_FONT: OnceLock<Font> = OnceLock::new();
+
+/// Clears the window and sets up drawing in arena units, letterboxed.
+pub fn begin_frame() {
+    clear_background(BLACK);
+    set_camera(&View::current().camera());
+    draw_rectangle(0., 0., ARENA.x, ARENA.y, BG_COLOR);
+}
+
+/// `launch_key` names the key that launches, for the prompt.
+pub fn draw_game_ui(lives: u8, ball_status: &Status, dead_balls_pos: &Vec<Vec2>, launch_key: &str) {
     draw_ball_lives(dead_balls_pos, lives);
-    draw_info_text(ball_status, lives, won);
+    draw_info_text(ball_status, launch_key);
 }
 
-pub fn draw_world(ball: &Ball, bricks: &[Brick], platform: &Platform, particles: &[Particle]) {
-    platform_draw(platform);
-    ball_draw(ball);
+pub fn draw_world(
+    balls: &[Ball],
+    bricks: &[Brick],
+    platform: &Platform,
+    particles: &[Particle],
+    powerups: &[PowerUp],
+    alpha: f32,
+) {
+    platform_draw(platform, alpha);
+    for ball in balls {
+        ball_draw(ball, alpha);
+    }
     for b in bricks.iter().filter(|b| b.active) {
         brick_draw(b);
     }
+    for p in powerups {
+        powerup_draw(p);
+    }
     for p in particles {
         particle_draw(p);
     }
 }
 
-fn brick_draw(brick: &Brick) {
-    let b = brick;
-    draw_rectangle(b.pos.x, b.pos.y, b.width, b.height, b.color);
-    draw_rectangle_lines(
-        b.pos.x,
-        b.pos.y,
-        b.width,
-        b.height,
-        2.,
-        Color::new(1.0, 1.0, 1.0, 0.3),
+pub fn draw_score(score: u32, multiplier: u32) {
+    draw_text_center_x(&format!("{score:06}"), MARGIN + 8., 28, WHITE);
+    if multiplier > 1 {
+        draw_text_center_x(&format!("x{multiplier}"), MARGIN + 30., 20, GOLD);
+    }
+}
+
+/// Endless mode's rows survived and a bar filling up until the next row
+/// comes in, over the field, and the line the bricks must not reach.
+pub fn draw_descent(descent: &Descent, line: f32) {
+    let y = 84.;
+    draw_text_global(
+        &format!("ROWS {}", descent.rows),
+        12.,
+        y - 8.,
+        18,
+        LIGHTGRAY,
     );
+    let progress = descent.progress();
+    let mut color = Color::new(1., 1. - progress * 0.7, 1. - progress * 0.7, 0.6);
+    draw_rectangle(0., y, ARENA.x * progress, 3., color);
+    color.a = 0.25;
+    draw_line(0., line, ARENA.x, line, 1., color);
 }
 
-fn ball_draw(ball: &Ball) {
+pub fn draw_popups(popups: &[Popup]) {
+    for popup in popups {
+        let mut color = popup.color;
+        color.a = popup.life.clamp(0., 1.);
+        draw_text_center_at(&popup.text, popup.pos, 18, color);
+    }
+}
+
+/// Lists active power-up effects with their remaining time, top right.
+pub fn draw_effects(effects: &[ActiveEffect]) {
+    let x = ARENA.x - 140.;
+    for (i, effect) in effects.iter().enumerate() {
+        let y = MARGIN + 6. + i as f32 * 22.;
+        let text = format!("{:<7}{:>3.0}s", effect.kind.name(), effect.remaining.ceil());
+        draw_text_global(&text, x, y, 18, effect.kind.color());
+    }
+}
+
+pub fn brick_draw(brick: &Brick) {
+    let b = brick;
+    draw_rectangle(b.pos.x, b.pos.y, b.width, b.height, b.display_color());
+    match b.kind {
+        BrickKind::Normal => {
+            draw_rectangle_lines(
+                b.pos.x,
+                b.pos.y,
+                b.width,
+                b.height,
+                2.,
+                Color::new(1.0, 1.0, 1.0, 0.3),
+            );
+        }
+        BrickKind::Steel => {
+            draw_rectangle_lines(b.pos.x, b.pos.y, b.width, b.height, 4., LIGHTGRAY);
+        }
+        BrickKind::Explosive => {
+            draw_rectangle_lines(b.pos.x, b.pos.y, b.width, b.height, 2., RED);
+            let center = b.center();
+            draw_circle(center.x, center.y, b.height / 4., RED);
+        }
+    }
+}
+
+/// Outline of a brick-sized slot, bright when picked or under the cursor.
+pub fn draw_slot(rect: Rect, highlight: bool) {
+    let (thickness, color) = if highlight {
+        (2., GOLD)
+    } else {
+        (1., Color::new(1., 1., 1., 0.1))
+    };
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, thickness, color);
+}
+
+fn ball_draw(ball: &Ball, alpha: f32) {
     if ball.status != Status::Dead {
-        draw_circle(ball.pos.x, ball.pos.y, ball.radius, YELLOW);
+        let color = if ball.piercing { VIOLET } else { YELLOW };
+        let pos = ball.prev_pos.lerp(ball.pos, alpha);
+        draw_circle(pos.x, pos.y, ball.radius, color);
     }
 }
 
-fn platform_draw(platform: &Platform) {
-    draw_rectangle(
-        platform.pos.x,
-        platform.pos.y,
-        platform.width,
-        platform.height,
-        WHITE,
+fn powerup_draw(powerup: &PowerUp) {
+    let r = powerup.bounds();
+    let color = powerup.kind.color();
+    let radius = r.h / 2.;
+    draw_rectangle(r.x + radius, r.y, r.w - r.h, r.h, color);
+    draw_circle(r.x + radius, r.y + radius, radius, color);
+    draw_circle(r.x + r.w - radius, r.y + radius, radius, color);
+
+    let label = powerup.kind.label();
+    let font = DEFAULT_FONT.get().expect("Font not loaded");
+    let center = get_text_center(label, Some(font), 16, 1.0, 0.0);
+    draw_text_global(
+        label,
+        r.x + r.w / 2. - center.x,
+        r.y + radius - center.y,
+        16,
+        BLACK,
     );
 }
 
+fn platform_draw(platform: &Platform, alpha: f32) {
+    let color = if platform.sticky { YELLOW } else { WHITE };
+    let pos = platform.prev_pos.lerp(platform.pos, alpha);
+    draw_rectangle(pos.x, pos.y, platform.width, platform.height, color);
+}
+
 fn particle_draw(particle: &Particle) {
     let mut color = particle.color;
     color.a = particle.life; // Apply alpha based on life
     draw_circle(particle.pos.x, particle.pos.y, 2., color);
 }
 
-fn draw_info_text(ball_status: &Status, lives: u8, won: bool) {
-    let info_pos_y = screen_height() - 200.;
-    let restart_text = "TOUCH / PRESS SPACE TO RESTART";
-    if won {
-        draw_text_center_x("GAME CLEARED", info_pos_y - 100., 40, LIME);
-        draw_text_center_x(restart_text, info_pos_y, 20, GRAY);
-        return;
-    }
-    match ball_status {
-        Status::Start => draw_text_center_x("TOUCH / PRESS SPACE TO LAUNCH", info_pos_y as f32, 20, GRAY),
-        Status::Dead => {
-            if lives == 0 {
-                draw_text_center_x("GAME OVER", info_pos_y - 100., 40, RED);
-                draw_text_center_x(restart_text, info_pos_y, 20, GRAY);
-            }
-        }
-        _ => {}
+fn draw_info_text(ball_status: &Status, launch_key: &str) {
+    if *ball_status == Status::Start {
+        let info_pos_y = ARENA.y - 200.;
+        draw_text_center_x(
+            &format!("TOUCH / CLICK / PRESS {launch_key} TO LAUNCH"),
+            info_pos_y,
+            20,
+            GRAY,
+        );
     }
 }
 
-const MARGIN: f32 = 30.;
-const SPACING: f32 = BALL_RADIUS * 2.5;
+pub fn draw_level_intro(number: usize, name: &str, alpha: f32) {
+    let info_pos_y = ARENA.y - 200.;
+    let mut title = WHITE;
+    title.a = alpha;
+    let mut subtitle = GRAY;
+    subtitle.a = alpha;
+    draw_text_center_x(&format!("LEVEL {number}"), info_pos_y - 100., 40, title);
+    draw_text_center_x(&name.to_uppercase(), info_pos_y - 60., 20, subtitle);
+}
+
+pub fn draw_name_entry(score: u32, name: &str, confirm_key: &str) {
+    draw_overlay_background();
+    let y = ARENA.y / 2. - 80.;
+    draw_text_center_x("NEW HIGH SCORE", y, 40, GOLD);
+    draw_text_center_x(&format!("{score}"), y + 40., 28, WHITE);
+
+    // Blinking cursor after the name typed so far
+    let cursor = if (get_time() * 2.) as i64 % 2 == 0 {
+        "_"
+    } else {
+        " "
+    };
+    draw_text_center_x(&format!("{name}{cursor}"), y + 100., 32, WHITE);
+    draw_text_center_x(
+        &format!("TYPE YOUR NAME, PRESS {confirm_key}"),
+        y + 150.,
+        20,
+        GRAY,
+    );
+}
+
+/// `keys` name the keys for the previous and next table and for closing.
+pub fn draw_high_scores(title: &str, entries: &[Entry], highlight: Option<usize>, keys: [&str; 3]) {
+    draw_overlay_background();
+    let top = 120.;
+    draw_text_center_x(&format!("HIGH SCORES - {title}"), top, 32, WHITE);
+    if entries.is_empty() {
+        draw_text_center_x("NO SCORES YET", top + 80., 20, GRAY);
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let color = if highlight == Some(i) {
+            GOLD
+        } else {
+            LIGHTGRAY
+        };
+        let line = format!("{:>2}. {:<10} {:>8}", i + 1, entry.name, entry.score);
+        draw_text_center_x(&line, top + 50. + i as f32 * 30., 22, color);
+    }
+    let [previous, next, close] = keys;
+    draw_text_center_x(
+        &format!("{previous} / {next} FOR OTHER MODES"),
+        ARENA.y - 90.,
+        16,
+        DARKGRAY,
+    );
+    draw_text_center_x(
+        &format!("TOUCH / PRESS {close} TO CLOSE"),
+        ARENA.y - 60.,
+        20,
+        GRAY,
+    );
+}
+
+/// Playback position and the `hint` for its controls, along the bottom of
+/// the screen.
+pub fn draw_replay_status(tick: usize, ticks: usize, speed: u32, paused: bool, hint: &str) {
+    let y = ARENA.y - 40.;
+    let state = if tick >= ticks {
+        "END".to_owned()
+    } else if paused {
+        "PAUSED".to_owned()
+    } else {
+        format!("x{speed}")
+    };
+    draw_text_center_x(&format!("REPLAY {tick}/{ticks} {state}"), y, 20, ORANGE);
+    draw_text_center_x(hint, y + 24., 16, DARKGRAY);
+}
+
+pub fn draw_title() {
+    let y = ARENA.y / 3.;
+    draw_text_center_x("RAYBALL", y, 72, WHITE);
+    draw_text_center_x("BREAK EVERY BRICK", y + 40., 20, GRAY);
+}
+
+/// Big centered heading for a menu screen, with an optional line under it.
+pub fn draw_heading(title: &str, subtitle: &str, color: Color) {
+    let y = ARENA.y / 3.;
+    draw_text_center_x(title, y, 48, color);
+    if !subtitle.is_empty() {
+        draw_text_center_x(subtitle, y + 40., 24, WHITE);
+    }
+}
+
+/// Heading for screens whose menu fills most of the height.
+pub fn draw_page_title(title: &str) {
+    draw_text_center_x(title, 70., 36, WHITE);
+}
 
-pub fn get_ball_lives_pos(i: u8) -> Vec2 {
-    vec2(MARGIN + ((i as f32 - 1.) * SPACING), MARGIN)
+/// Feedback line above the hint at the bottom of a menu screen.
+pub fn draw_message(text: &str, color: Color) {
+    draw_text_center_x(text, ARENA.y - 60., 20, color);
 }
 
+pub fn draw_hint(text: &str) {
+    draw_text_center_x(text, ARENA.y - 30., 16, DARKGRAY);
+}
+
+const MENU_ITEM_HEIGHT: f32 = 36.;
+/// Most height a menu takes up; longer menus get shorter items to fit.
+const MENU_MAX_HEIGHT: f32 = 11. * MENU_ITEM_HEIGHT;
+
+/// Screen area of menu item `i` out of `count`, for drawing and hit testing.
+pub fn menu_item_rect(i: usize, count: usize) -> Rect {
+    let width = 360.;
+    let height = MENU_ITEM_HEIGHT.min(MENU_MAX_HEIGHT / count as f32);
+    let top = ARENA.y * 0.55 - count as f32 * height / 2.;
+    Rect::new(
+        (ARENA.x - width) / 2.,
+        top + i as f32 * height,
+        width,
+        height,
+    )
+}
+
+pub fn draw_menu(items: &[String], selected: usize) {
+    for (i, item) in items.iter().enumerate() {
+        let rect = menu_item_rect(i, items.len());
+        let (text, color) = if i == selected {
+            (format!("> {item} <"), GOLD)
+        } else {
+            (item.clone(), LIGHTGRAY)
+        };
+        draw_text_center_x(&text, rect.y + rect.h * 0.7, 24, color);
+    }
+}
+
+pub fn draw_level_complete(number: usize, score: u32, confirm_key: &str) {
+    draw_overlay_background();
+    draw_heading(&format!("LEVEL {number} CLEAR"), &format!("{score}"), LIME);
+    draw_text_center_x(
+        &format!("TOUCH / PRESS {confirm_key} TO CONTINUE"),
+        ARENA.y - 200.,
+        20,
+        GRAY,
+    );
+}
+
+pub fn draw_overlay_background() {
+    draw_rectangle(0., 0., ARENA.x, ARENA.y, Color::new(0., 0., 0., 0.8));
+}
+
+const MARGIN: f32 = 30.;
 fn draw_ball_lives(dead_balls_pos: &Vec<Vec2>, lives: u8) {
     let ghost_color = Color::new(1.0, 1.0, 1.0, 0.2);
     for pos in dead_balls_pos {
@@ -86,23 +334,34 @@ fn draw_ball_lives(dead_balls_pos: &Vec<Vec2>, lives: u8) {
     }
 
     for i in 1..lives {
-        let pos = get_ball_lives_pos(i);
+        let pos = ball::get_ball_lives_pos(i);
         draw_circle(pos.x, pos.y, BALL_RADIUS, ghost_color);
     }
 }
 
 fn draw_text_center_x(text: &str, y: f32, font_size: u16, color: Color) {
     let font = DEFAULT_FONT.get().expect("Font not loaded");
-    let center = get_text_center(text, Some(font), font_size as u16, 1.0, 0.0);
-    let x = (screen_width() / 2.) - center.x;
+    let center = get_text_center(text, Some(font), font_size, 1.0, 0.0);
+    let x = (ARENA.x / 2.) - center.x;
     draw_text_global(text, x, y, font_size, color);
 }
 
+fn draw_text_center_at(text: &str, pos: Vec2, font_size: u16, color: Color) {
+    let font = DEFAULT_FONT.get().expect("Font not loaded");
+    let center = get_text_center(text, Some(font), font_size, 1.0, 0.0);
+    draw_text_global(text, pos.x - center.x, pos.y - center.y, font_size, color);
+}
+
 pub fn draw_text_global(text: &str, x: f32, y: f32, font_size: u16, color: Color) {
     let font = DEFAULT_FONT.get().expect("Font not loaded");
+    // Rasterize at the size the text ends up on screen, then scale back to
+    // arena units, so scaled up text stays sharp
+    let pixels = View::current().scale * screen_dpi_scale();
+    let raster_size = ((font_size as f32 * pixels).round() as u16).max(1);
     let params = TextParams {
         font: Some(font),
-        font_size,
+        font_size: raster_size,
+        font_scale: font_size as f32 / raster_size as f32,
         color,
         ..Default::default()
     };
@@ -0,0 +1,110 @@
+//! Fits the fixed logical arena to whatever size the window is.
+//!
+//! Everything is laid out and simulated in arena units, `ARENA` wide and
+//! high. The arena is scaled up or down to fit the window and centered,
+//! leaving bars along the sides that don't match its aspect ratio.
+
+use crate::constants::ARENA;
+use macroquad::prelude::*;
+
+/// Where the arena sits in a window.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct View {
+    /// Window pixels per arena unit.
+    pub scale: f32,
+    /// Window position of the arena's top left corner.
+    pub offset: Vec2,
+}
+
+impl View {
+    /// Fits the arena into a window `size` pixels large.
+    pub fn fit(size: Vec2) -> View {
+        let scale = (size.x / ARENA.x).min(size.y / ARENA.y);
+        View {
+            scale,
+            offset: (size - ARENA * scale) / 2.,
+        }
+    }
+
+    /// The view of the window as it is this frame.
+    pub fn current() -> View {
+        View::fit(vec2(screen_width(), screen_height()))
+    }
+
+    /// Arena position of window position `pos`.
+    pub fn to_arena(&self, pos: Vec2) -> Vec2 {
+        (pos - self.offset) / self.scale
+    }
+
+    /// A camera drawing arena units into the arena's part of the window.
+    pub fn camera(&self) -> Camera2D {
+        // The viewport is in physical pixels, counted from the bottom left
+        let dpi = screen_dpi_scale();
+        let size = ARENA * self.scale * dpi;
+        let bottom = screen_height() * dpi - self.offset.y * dpi - size.y;
+        Camera2D {
+            target: ARENA / 2.,
+            zoom: vec2(2. / ARENA.x, 2. / ARENA.y),
+            viewport: Some((
+                (self.offset.x * dpi).round() as i32,
+                bottom.round() as i32,
+                size.x.round() as i32,
+                size.y.round() as i32,
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+/// Where the mouse is in the arena. Outside of it when over the bars.
+pub fn mouse_position() -> Vec2 {
+    View::current().to_arena(macroquad::input::mouse_position().into())
+}
+
+/// How far the mouse moved since last frame, in arena units. Unlike the
+/// position, this keeps counting while the cursor is grabbed.
+pub fn mouse_delta() -> Vec2 {
+    // Macroquad measures from the current position back to the last one, in
+    // halves of the window
+    let window = vec2(screen_width(), screen_height());
+    -mouse_delta_position() * window / 2. / View::current().scale
+}
+
+/// Where `touch` is in the arena.
+pub fn touch_position(touch: &Touch) -> Vec2 {
+    View::current().to_arena(touch.position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_shape_fills_the_window() {
+        let view = View::fit(ARENA * 2.);
+        assert_eq!(view.scale, 2.);
+        assert_eq!(view.offset, Vec2::ZERO);
+    }
+
+    #[test]
+    fn wider_windows_get_bars_left_and_right() {
+        let view = View::fit(vec2(ARENA.x * 2., ARENA.y));
+        assert_eq!(view.scale, 1.);
+        assert_eq!(view.offset, vec2(ARENA.x / 2., 0.));
+    }
+
+    #[test]
+    fn taller_windows_get_bars_top_and_bottom() {
+        let view = View::fit(vec2(ARENA.x / 2., ARENA.y));
+        assert_eq!(view.scale, 0.5);
+        assert_eq!(view.offset, vec2(0., ARENA.y / 4.));
+    }
+
+    #[test]
+    fn window_positions_map_back_into_the_arena() {
+        let view = View::fit(vec2(ARENA.x * 3., ARENA.y * 2.));
+        assert_eq!(view.to_arena(view.offset), Vec2::ZERO);
+        let corner = view.offset + ARENA * view.scale;
+        assert_eq!(view.to_arena(corner), ARENA);
+    }
+}
@@ -0,0 +1,657 @@
+use crate::components::particle::Direction;
+use crate::components::powerup::EFFECT_DURATION;
+use crate::components::*;
+use crate::constants::*;
+use crate::input::TickInput;
+use crate::levels::generator;
+use crate::levels::{Course, Pace};
+use crate::rng::Rng;
+use crate::systems::broadphase::BrickGrid;
+use crate::systems::descent::{self, Descent};
+use crate::systems::physics;
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy)]
+pub enum GameEvent {
+    BallHitWall,
+    BallDropped(Vec2),
+    BallHitPlatform(Vec2),
+    /// A ball hit a brick in the given layout row.
+    BrickCollision(Vec2, Direction, usize),
+    BrickExploded(Vec2),
+    BrickDestroyed(Vec2, u32),
+    PowerUpCaught(PowerUpKind),
+    /// A life was lost and a new ball is flying back to the paddle.
+    BallRespawned,
+    /// Cleared a stage and moved on to this one.
+    StageStarted(usize),
+    /// Endless mode pushed a new row in at the top.
+    RowAdded,
+    GameOver,
+    GameWon,
+}
+
+/// Angle, in radians, between the balls a multiball split produces.
+const SPLIT_ANGLE: f32 = 0.35;
+
+/// The whole game simulation. Knows nothing about the window: the arena size
+/// is fixed at creation and input arrives one tick at a time, so it runs the
+/// same in the game, in tests and in headless tools.
+pub struct World {
+    pub arena: Vec2,
+    /// Balls in play. Never empty: the last ball stays around after it drops.
+    pub balls: Vec<Ball>,
+    /// Change the list itself through `set_bricks`, which keeps `grid` in step.
+    bricks: Vec<Brick>,
+    /// Where the live bricks are, for finding the ones near a ball quickly.
+    grid: BrickGrid,
+    pub platform: Platform,
+    pub particles: Vec<Particle>,
+    pub popups: Vec<Popup>,
+    pub powerups: Vec<PowerUp>,
+    pub effects: Vec<ActiveEffect>,
+    pub lives: u8,
+    pub score: u32,
+    /// Brick hits since a ball last touched the paddle.
+    pub combo: u32,
+    pub death_pos: Vec<Vec2>,
+    pub won: bool,
+    /// Stages this run plays through.
+    pub course: Course,
+    pub stage: usize,
+    pub stage_name: String,
+    pub par_time: f32,
+    pub stage_time: f32,
+    pub drop_chance: f32,
+    pub pace: Pace,
+    /// Speed every ball flies at, before power-ups. Starts at the stage's
+    /// pace and builds up over the stage.
+    pub speed: f32,
+    /// Highest layout row a ball has hit since the stage or life started.
+    top_row: usize,
+    /// Rows coming in, on an endless run.
+    pub descent: Option<Descent>,
+    pub intro_timer: f32,
+    /// Ticks simulated since the world was created.
+    pub ticks: u64,
+    rng: Rng,
+    /// Events from the ticks so far, for the caller to react to.
+    events: Vec<GameEvent>,
+}
+
+impl World {
+    pub fn new(arena: Vec2, seed: u64) -> Self {
+        Self::with_course(arena, seed, Course::default())
+    }
+
+    /// A world that plays `course` instead of the campaign from the start.
+    pub fn with_course(arena: Vec2, seed: u64, course: Course) -> Self {
+        let first = course.first();
+        let mut world = Self {
+            arena,
+            balls: vec![Ball::new()],
+            bricks: Vec::new(),
+            grid: BrickGrid::new(arena),
+            platform: Platform::new(arena),
+            particles: Vec::new(),
+            popups: Vec::new(),
+            powerups: Vec::new(),
+            effects: Vec::new(),
+            lives: MAX_LIVES,
+            score: 0,
+            combo: 0,
+            death_pos: Vec::new(),
+            won: false,
+            course,
+            stage: first,
+            stage_name: String::new(),
+            par_time: 0.,
+            stage_time: 0.,
+            drop_chance: 0.,
+            pace: Pace::default(),
+            speed: 0.,
+            top_row: 0,
+            descent: None,
+            intro_timer: 0.,
+            ticks: 0,
+            rng: Rng::new(seed),
+            events: Vec::new(),
+        };
+
+        world.load_stage(first);
+        world.sync_ball_position();
+        world
+    }
+
+    /// Advances the simulation by one fixed step of `TICK_DT`.
+    pub fn tick(&mut self, input: TickInput) {
+        let dt = TICK_DT;
+        self.ticks += 1;
+
+        for ball in &mut self.balls {
+            ball.prev_pos = ball.pos;
+        }
+        self.platform.prev_pos = self.platform.pos;
+
+        self.intro_timer = (self.intro_timer - dt).max(0.);
+        if !self.won {
+            self.stage_time += dt;
+        }
+        // A run that has ended stays as it ended, even with nothing left
+        if !self.is_over() && !self.bricks.iter().any(|b| b.active && b.is_breakable()) {
+            match self.descent {
+                // Nothing left to break, so the next row comes right away
+                Some(_) => self.add_row(),
+                None => self.stage_cleared(),
+            }
+        }
+
+        self.apply_input(input, dt);
+        if self.balls.iter().any(|b| b.status == Status::Running) {
+            self.speed_up(self.pace.speed_up * dt);
+            if self.descent.as_mut().is_some_and(|d| d.advance(dt)) {
+                self.add_row();
+            }
+        }
+
+        self.update_effects(dt);
+        self.move_balls(dt);
+        self.update_powerups(dt);
+
+        self.particles.iter_mut().for_each(|p| p.update(dt));
+        self.particles.retain(|p| p.life > 0.0);
+        self.popups.iter_mut().for_each(|p| p.update(dt));
+        self.popups.retain(|p| p.life > 0.0);
+    }
+
+    /// Fingerprint of everything that affects how the game plays on, for
+    /// telling whether two runs ended up in exactly the same state. Cosmetic
+    /// state like particles and popups is left out.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = StateHash::default();
+        hash.u64(self.ticks);
+        for ball in &self.balls {
+            hash.vec2(ball.pos);
+            hash.vec2(ball.velocity);
+            hash.f32(ball.spin);
+            hash.u64(ball.status as u64);
+        }
+        hash.f32(self.speed);
+        hash.vec2(self.platform.pos);
+        hash.f32(self.platform.width);
+        for brick in &self.bricks {
+            hash.u64(brick.active as u64);
+            hash.u64(brick.hp as u64);
+        }
+        for powerup in &self.powerups {
+            hash.vec2(powerup.pos);
+            hash.u64(powerup.kind as u64);
+        }
+        for effect in &self.effects {
+            hash.u64(effect.kind as u64);
+            hash.f32(effect.remaining);
+        }
+        hash.u64(self.lives as u64);
+        hash.u64(self.score as u64);
+        hash.u64(self.combo as u64);
+        hash.u64(self.stage as u64);
+        if let Some(descent) = &self.descent {
+            hash.u64(descent.rows as u64);
+            hash.f32(descent.timer);
+            hash.f32(descent.interval);
+        }
+        hash.u64(self.rng.clone().next_u64());
+        hash.0
+    }
+
+    /// Whether the game has ended, won or lost. Only a launch, which starts
+    /// a new game, changes anything after that.
+    pub fn is_over(&self) -> bool {
+        self.won || self.lives == 0
+    }
+
+    /// Takes the events raised since the last call.
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn apply_input(&mut self, input: TickInput, dt: f32) {
+        if input.left {
+            self.platform.move_left(dt);
+        }
+        if input.right {
+            self.platform.move_right(dt);
+        }
+        if input.drag != 0. {
+            self.platform.move_delta(input.drag);
+        }
+        if let Some(x) = input.pointer {
+            self.platform.move_to(x);
+        }
+        self.platform.track_velocity(dt);
+        if input.launch {
+            self.handle_launch_input();
+        }
+    }
+
+    fn move_balls(&mut self, dt: f32) {
+        let ball_dt = dt * self.ball_speed_scale();
+        let flight = physics::Flight {
+            speed: self.speed,
+            min_rise: self.pace.min_rise(),
+        };
+        let mut events = Vec::new();
+
+        for ball in &mut self.balls {
+            match ball.status {
+                Status::Start => {
+                    physics::snap_ball_to_platform(ball, &self.platform);
+                }
+                Status::Running => {
+                    events.extend(physics::move_ball(
+                        ball,
+                        &self.platform,
+                        &mut self.bricks,
+                        &mut self.grid,
+                        self.arena,
+                        flight,
+                        ball_dt,
+                    ));
+                }
+                Status::Dead => {}
+                Status::Spawning => {
+                    if !physics::transition_ball(ball, &self.platform, dt) {
+                        ball.status = Status::Start;
+                        physics::snap_ball_to_platform(ball, &self.platform);
+                    }
+                }
+            }
+        }
+
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
+    /// Status the HUD reports: waiting to launch if any ball is, dead only
+    /// once every ball is.
+    pub fn ball_status(&self) -> Status {
+        let statuses = || self.balls.iter().map(|b| b.status);
+        if statuses().any(|s| s == Status::Start) {
+            Status::Start
+        } else if statuses().all(|s| s == Status::Dead) {
+            Status::Dead
+        } else {
+            self.balls[0].status
+        }
+    }
+
+    fn handle_event(&mut self, event: GameEvent) {
+        self.events.push(event);
+        match event {
+            GameEvent::BallDropped(pos) => {
+                if self.balls.iter().any(|b| b.status != Status::Dead) {
+                    // Other balls are still in play, this one is just gone
+                    self.balls.retain(|b| b.status != Status::Dead);
+                    return;
+                }
+
+                self.balls.truncate(1);
+                self.combo = 0;
+                self.death_pos.push(pos);
+                self.lives -= 1;
+                self.reset_pace();
+                self.clear_powerups();
+                if self.lives > 0 {
+                    let ball = &mut self.balls[0];
+                    ball.reset();
+                    ball.pos = ball::get_ball_lives_pos(self.lives);
+                    ball.status = Status::Spawning;
+                    self.events.push(GameEvent::BallRespawned);
+                } else {
+                    self.events.push(GameEvent::GameOver);
+                }
+            }
+            GameEvent::BallHitWall => {}
+            GameEvent::BallHitPlatform(hit_point) => {
+                self.combo = 0;
+                self.particles.extend(Particle::spawn_particles(
+                    hit_point,
+                    Direction::Up,
+                    &mut self.rng,
+                ));
+            }
+            GameEvent::BrickCollision(hit_point, direction, row) => {
+                self.combo += 1;
+                self.speed_up(self.pace.brick_speed_up);
+                if row < self.top_row {
+                    let climbed = self.top_row - row;
+                    self.speed_up(climbed as f32 * self.pace.row_speed_up);
+                    self.top_row = row;
+                }
+                self.particles.extend(Particle::spawn_particles(
+                    hit_point,
+                    direction,
+                    &mut self.rng,
+                ));
+            }
+            GameEvent::BrickExploded(center) => {
+                for direction in [Direction::Up, Direction::Down] {
+                    self.particles.extend(Particle::spawn_particles(
+                        center,
+                        direction,
+                        &mut self.rng,
+                    ));
+                }
+            }
+            GameEvent::BrickDestroyed(center, points) => {
+                // Endless runs score by rows survived instead
+                if self.descent.is_none() {
+                    self.award_points(center, points * self.multiplier());
+                }
+                if let Some(powerup) = PowerUp::roll_drop(center, self.drop_chance, &mut self.rng) {
+                    self.powerups.push(powerup);
+                }
+            }
+            GameEvent::PowerUpCaught(_)
+            | GameEvent::BallRespawned
+            | GameEvent::StageStarted(_)
+            | GameEvent::RowAdded
+            | GameEvent::GameOver
+            | GameEvent::GameWon => {}
+        }
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.combo / COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+
+    fn award_points(&mut self, pos: Vec2, points: u32) {
+        if points == 0 {
+            return;
+        }
+        self.score += points;
+        self.popups
+            .push(Popup::new(pos, format!("+{points}"), WHITE));
+    }
+
+    fn update_powerups(&mut self, dt: f32) {
+        let paddle = self.platform.bounds();
+        let floor = self.arena.y;
+        let mut caught = Vec::new();
+        self.powerups.retain_mut(|p| {
+            p.update(dt);
+            if p.bounds().overlaps(&paddle) {
+                caught.push(p.kind);
+                return false;
+            }
+            p.pos.y < floor
+        });
+        for kind in caught {
+            self.catch_powerup(kind);
+        }
+    }
+
+    fn catch_powerup(&mut self, kind: PowerUpKind) {
+        log::debug!("Caught power-up {kind:?}");
+        self.events.push(GameEvent::PowerUpCaught(kind));
+        match kind {
+            PowerUpKind::ExtraLife => {
+                self.lives = (self.lives + 1).min(LIVES_CAP);
+                return;
+            }
+            PowerUpKind::Multiball => {
+                self.split_ball();
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(opposite) = kind.opposite() {
+            self.effects.retain(|e| e.kind != opposite);
+        }
+        match self.effects.iter_mut().find(|e| e.kind == kind) {
+            Some(effect) => effect.remaining = EFFECT_DURATION,
+            None => self.effects.push(ActiveEffect {
+                kind,
+                remaining: EFFECT_DURATION,
+            }),
+        }
+        self.apply_effects();
+    }
+
+    fn update_effects(&mut self, dt: f32) {
+        if self.effects.is_empty() {
+            return;
+        }
+        self.effects.iter_mut().for_each(|e| e.remaining -= dt);
+        self.effects.retain(|e| e.remaining > 0.);
+        self.apply_effects();
+    }
+
+    fn has_effect(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    fn apply_effects(&mut self) {
+        let width_scale = if self.has_effect(PowerUpKind::WidePaddle) {
+            1.5
+        } else if self.has_effect(PowerUpKind::NarrowPaddle) {
+            0.6
+        } else {
+            1.
+        };
+        self.platform.set_width_scale(width_scale);
+        self.platform.sticky = self.has_effect(PowerUpKind::StickyPaddle);
+        let piercing = self.has_effect(PowerUpKind::PiercingBall);
+        self.balls.iter_mut().for_each(|b| b.piercing = piercing);
+    }
+
+    /// Splits the first ball in flight into three, fanning out from its heading.
+    fn split_ball(&mut self) {
+        let Some(ball) = self.balls.iter().find(|b| b.status == Status::Running) else {
+            return;
+        };
+        let room = MAX_BALLS.saturating_sub(self.balls.len());
+        let splits: Vec<Ball> = [-SPLIT_ANGLE, SPLIT_ANGLE]
+            .iter()
+            .take(room)
+            .map(|&angle| {
+                let mut split = ball.clone();
+                split.velocity = Vec2::from_angle(angle).rotate(ball.velocity);
+                split
+            })
+            .collect();
+        self.balls.extend(splits);
+    }
+
+    fn speed_up(&mut self, gain: f32) {
+        self.speed = (self.speed + gain).min(self.pace.max_speed);
+    }
+
+    /// Back to the stage's starting speed, with no rows climbed yet.
+    fn reset_pace(&mut self) {
+        self.speed = self.pace.speed;
+        self.top_row = self.bricks.iter().map(|b| b.row + 1).max().unwrap_or(0);
+    }
+
+    fn ball_speed_scale(&self) -> f32 {
+        if self.has_effect(PowerUpKind::SlowBall) {
+            0.6
+        } else if self.has_effect(PowerUpKind::FastBall) {
+            1.4
+        } else {
+            1.
+        }
+    }
+
+    /// Drops falling capsules and ends every active effect.
+    fn clear_powerups(&mut self) {
+        self.powerups.clear();
+        self.effects.clear();
+        self.apply_effects();
+    }
+
+    fn sync_ball_position(&mut self) {
+        for ball in &mut self.balls {
+            physics::snap_ball_to_platform(ball, &self.platform);
+            ball.prev_pos = ball.pos;
+        }
+    }
+
+    fn load_stage(&mut self, stage: usize) {
+        let level = self.course.stage(stage);
+        self.set_bricks(level.bricks(self.arena.x));
+        self.stage = stage;
+        self.stage_name = level.name;
+        self.par_time = level.par_time;
+        self.stage_time = 0.;
+        self.combo = 0;
+        self.drop_chance = level.drop_chance;
+        self.pace = level.pace;
+        self.descent = None;
+        if self.course == Course::Endless {
+            self.descent = Some(Descent::default());
+            for _ in 0..descent::START_ROWS {
+                self.push_row();
+            }
+        }
+        self.reset_pace();
+        self.clear_powerups();
+        self.intro_timer = LEVEL_INTRO_TIME;
+    }
+
+    /// Pushes the field down a row with a new one at the top, a little
+    /// tougher than the last.
+    fn push_row(&mut self) {
+        let depth = self.descent.as_ref().map_or(0, |d| d.rows);
+        let row = generator::endless_row(&mut self.rng, depth);
+        let mut bricks = std::mem::take(&mut self.bricks);
+        descent::push_row(&mut bricks, &row, self.arena.x);
+        self.set_bricks(bricks);
+        self.top_row += 1;
+        descent::shove_balls(&mut self.balls, &self.bricks);
+    }
+
+    /// A row comes in on an endless run: it scores if the field stays clear
+    /// of the paddle, and ends the run if not.
+    fn add_row(&mut self) {
+        let Some(descent) = &mut self.descent else {
+            return;
+        };
+        descent.next_row();
+        self.push_row();
+        self.events.push(GameEvent::RowAdded);
+
+        if descent::reached(&self.bricks, self.platform.pos.y) {
+            self.overrun();
+            return;
+        }
+        let center = vec2(self.arena.x / 2., self.platform.pos.y - 60.);
+        self.award_points(center, descent::ROW_POINTS);
+    }
+
+    /// The bricks reached the paddle: every ball is lost at once.
+    fn overrun(&mut self) {
+        self.balls.truncate(1);
+        self.balls[0].die();
+        self.lives = 0;
+        self.combo = 0;
+        self.clear_powerups();
+        self.events.push(GameEvent::GameOver);
+    }
+
+    /// The bricks of the current stage, destroyed ones included.
+    pub fn bricks(&self) -> &[Brick] {
+        &self.bricks
+    }
+
+    /// Swaps in a new set of bricks.
+    pub fn set_bricks(&mut self, bricks: Vec<Brick>) {
+        self.grid.rebuild(&bricks);
+        self.bricks = bricks;
+    }
+
+    /// Moves on to the next stage, keeping lives; the last stage wins the game.
+    fn stage_cleared(&mut self) {
+        let seconds_under_par = (self.par_time - self.stage_time).max(0.) as u32;
+        let bonus = seconds_under_par * TIME_BONUS_PER_SECOND;
+        if bonus > 0 {
+            self.score += bonus;
+            let center = self.arena / 2.;
+            self.popups
+                .push(Popup::new(center, format!("TIME BONUS +{bonus}"), LIME));
+        }
+
+        if self.stage + 1 >= self.course.stage_count() {
+            self.won = true;
+            self.events.push(GameEvent::GameWon);
+            return;
+        }
+
+        self.load_stage(self.stage + 1);
+        if self.balls.iter().any(|b| b.status != Status::Dead) {
+            // Fly one ball back to the paddle from wherever it is
+            self.balls.retain(|b| b.status != Status::Dead);
+            self.balls.truncate(1);
+            let ball = &mut self.balls[0];
+            ball.velocity = Vec2::ZERO;
+            ball.status = Status::Spawning;
+        }
+        self.events.push(GameEvent::StageStarted(self.stage));
+    }
+
+    pub fn reset(&mut self) {
+        self.lives = MAX_LIVES;
+        self.score = 0;
+        self.death_pos = Vec::new();
+        self.won = false;
+        self.balls = vec![Ball::new()];
+        self.platform = Platform::new(self.arena);
+        self.sync_ball_position();
+        self.load_stage(self.course.first());
+    }
+
+    fn handle_launch_input(&mut self) {
+        if self.won {
+            self.reset();
+            return;
+        }
+        match self.ball_status() {
+            Status::Start => self
+                .balls
+                .iter_mut()
+                .filter(|b| b.status == Status::Start)
+                .for_each(|b| b.launch()),
+            Status::Dead if self.lives == 0 => self.reset(),
+            _ => {}
+        }
+    }
+}
+
+/// FNV-1a, which gives the same answer on every platform.
+struct StateHash(u64);
+
+impl Default for StateHash {
+    fn default() -> Self {
+        StateHash(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl StateHash {
+    fn u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u64(value.to_bits() as u64);
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+}
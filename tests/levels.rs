@@ -0,0 +1,74 @@
+use macroquad::prelude::*;
+use rayball::components::Brick;
+use rayball::components::bricks::{HEIGHT, WIDTH};
+use rayball::constants::ARENA;
+use rayball::levels::{CAMPAIGN, Level, Pace};
+
+const HEADER: &str = "[level]\nname = Test\npar = 30\n";
+const GRID: &str = "[legend]\nW = normal #FFFFFF\n\n[layout]\nWW\n";
+
+fn parse(level_keys: &str) -> Result<Level, String> {
+    Level::parse(&format!("{HEADER}{level_keys}\n{GRID}")).map_err(|e| e.to_string())
+}
+
+#[test]
+fn pace_defaults_when_left_out() {
+    assert_eq!(parse("").unwrap().pace, Pace::default());
+}
+
+#[test]
+fn pace_keys_are_read() {
+    let level = parse(
+        "speed = 500\nmax_speed = 800\nspeed_up = 0\nbrick_speed_up = 2.5\n\
+         row_speed_up = 20\nmin_angle = 30",
+    )
+    .unwrap();
+    assert_eq!(
+        level.pace,
+        Pace {
+            speed: 500.,
+            max_speed: 800.,
+            speed_up: 0.,
+            brick_speed_up: 2.5,
+            row_speed_up: 20.,
+            min_angle: 30.,
+        }
+    );
+    assert!((level.pace.min_rise() - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn bad_pace_is_an_error() {
+    let error = |keys| parse(keys).err().unwrap();
+    assert!(error("speed = 0").contains("invalid speed `0`"));
+    assert!(error("row_speed_up = -1").contains("invalid row_speed_up `-1`"));
+    assert!(error("min_angle = 75").contains("invalid min_angle `75`"));
+    assert!(error("speed = 900\nmax_speed = 800").contains("max_speed is below speed"));
+}
+
+#[test]
+fn campaign_levels_survive_being_written_out() {
+    for src in CAMPAIGN {
+        let level = Level::parse(src).unwrap();
+        let again = Level::parse(&level.to_text()).unwrap();
+        assert_eq!(again.name, level.name);
+        assert_eq!(again.par_time, level.par_time);
+        assert_eq!(again.drop_chance, level.drop_chance);
+        assert_eq!(again.pace, level.pace);
+        assert_eq!(again.rows, level.rows);
+    }
+}
+
+#[test]
+fn every_grid_slot_maps_back_to_itself() {
+    for row in 0..10 {
+        for col in 0..10 {
+            let pos = Brick::grid_pos(row, col, 10, ARENA.x);
+            let center = pos + vec2(WIDTH, HEIGHT) / 2.;
+            assert_eq!(Brick::grid_cell(center, 10, ARENA.x), Some((row, col)));
+            assert_eq!(Brick::grid_cell(pos, 10, ARENA.x), Some((row, col)));
+        }
+    }
+    assert_eq!(Brick::grid_cell(vec2(1., 300.), 10, ARENA.x), None);
+    assert_eq!(Brick::grid_cell(vec2(400., 50.), 10, ARENA.x), None);
+}
@@ -0,0 +1,150 @@
+use macroquad::prelude::*;
+use rayball::constants::TICK_RATE;
+use rayball::input::TickInput;
+use rayball::levels::Course;
+use rayball::replay::{self, Replay};
+use rayball::world::World;
+
+/// A minute of play that launches, sweeps the paddle back and forth and
+/// drags it now and then, then steers it with the mouse for a while.
+fn recorded_session() -> Replay {
+    let mut replay = Replay::new(7, vec2(800., 600.));
+    for i in 0..60 * TICK_RATE as usize {
+        let input = TickInput {
+            left: (i / 90) % 2 == 0,
+            right: (i / 90) % 2 == 1,
+            launch: i % 500 == 0,
+            drag: if i % 333 == 0 { -12.5 } else { 0. },
+            pointer: (i > 40 * TICK_RATE as usize).then_some((i % 800) as f32 * 0.75),
+        };
+        replay.inputs.push(input);
+    }
+    replay
+}
+
+#[test]
+fn text_round_trips() {
+    let replay = recorded_session();
+    let parsed = Replay::parse(&replay.to_text()).unwrap();
+    assert_eq!(parsed.seed, replay.seed);
+    assert_eq!(parsed.arena, replay.arena);
+    assert_eq!(parsed.inputs, replay.inputs);
+}
+
+#[test]
+fn playback_reproduces_the_session() {
+    let replay = recorded_session();
+    let mut live = replay.world();
+    for &input in &replay.inputs {
+        live.tick(input);
+    }
+
+    let parsed = Replay::parse(&replay.to_text()).unwrap();
+    let mut played = parsed.world();
+    for &input in &parsed.inputs {
+        played.tick(input);
+    }
+
+    assert_eq!(played.score, live.score);
+    assert_eq!(played.lives, live.lives);
+    assert_eq!(played.stage, live.stage);
+    assert_eq!(played.platform.pos, live.platform.pos);
+    let positions = |w: &World| w.balls.iter().map(|b| b.pos).collect::<Vec<_>>();
+    assert_eq!(positions(&played), positions(&live));
+    let bricks = |w: &World| w.bricks().iter().map(|b| b.hp).collect::<Vec<_>>();
+    assert_eq!(bricks(&played), bricks(&live));
+}
+
+#[test]
+fn playback_stops_where_the_game_ended() {
+    // Launches and steps aside from every ball, and keeps doing it after the
+    // game is lost, where a launch would start a new game
+    let mut replay = Replay::new(2, vec2(800., 600.));
+    let second = TICK_RATE as usize;
+    for i in 0..6 {
+        replay.inputs.extend(vec![TickInput::default(); second]);
+        replay.inputs.push(TickInput {
+            launch: true,
+            ..Default::default()
+        });
+        let dodge = TickInput {
+            left: i % 2 == 0,
+            right: i % 2 == 1,
+            ..Default::default()
+        };
+        replay.inputs.extend(vec![dodge; 10 * second]);
+    }
+    let world = replay.run();
+    assert!(world.is_over());
+    assert_eq!(world.lives, 0);
+    assert!(world.ticks < replay.inputs.len() as u64);
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let text = recorded_session().to_text();
+    let current = format!("replay v{}", replay::VERSION);
+    let text = text.replacen(&current, "replay v99", 1);
+    let err = Replay::parse(&text).err().unwrap();
+    assert!(err.contains("version"), "{err}");
+}
+
+#[test]
+fn truncated_input_is_an_error() {
+    let text = "# rayball replay v1\nseed 1\narena 800 600\nticks 10\n4 -\n5 L\n";
+    let err = Replay::parse(text).err().unwrap();
+    assert!(err.contains("expected 10 ticks"), "{err}");
+}
+
+#[test]
+fn older_versions_still_parse() {
+    let text = "# rayball replay v2\nseed 1\narena 800 600\nticks 6\n4 L\n2 S -3.5\n";
+    let replay = Replay::parse(text).unwrap();
+    assert_eq!(replay.inputs.len(), 6);
+    assert_eq!(replay.inputs[5].drag, -3.5);
+    assert!(replay.inputs.iter().all(|i| i.pointer.is_none()));
+}
+
+#[test]
+fn pointer_runs_parse_with_and_without_drag() {
+    let text = "# rayball replay v3\nseed 1\narena 800 600\nticks 3\n1 - @120.5\n2 S 4 @300\n";
+    let replay = Replay::parse(text).unwrap();
+    assert_eq!(replay.inputs[0].pointer, Some(120.5));
+    assert_eq!(replay.inputs[0].drag, 0.);
+    assert_eq!(replay.inputs[2].pointer, Some(300.));
+    assert_eq!(replay.inputs[2].drag, 4.);
+}
+
+#[test]
+fn the_course_is_recorded() {
+    let mut later_stage = recorded_session();
+    later_stage.course = Course::Campaign { first: 2 };
+    let parsed = Replay::parse(&later_stage.to_text()).unwrap();
+    assert_eq!(parsed.course, later_stage.course);
+    assert_eq!(parsed.world().stage, 2);
+
+    let level =
+        "[level]\nname = Lone\npar = 10\n\n[legend]\nB = normal #FFFFFF\n\n[layout]\n  B\nB.B\n";
+    let mut custom = recorded_session();
+    custom.course = Course::Custom(level.to_owned());
+    let parsed = Replay::parse(&custom.to_text()).unwrap();
+    assert_eq!(parsed.course, Course::Custom(level.trim_end().to_owned()));
+    assert_eq!(parsed.world().stage_name, "Lone");
+    assert_eq!(parsed.run().score, custom.run().score);
+
+    // Trailing spaces are empty cells that keep the rows off-centre
+    let level = "[level]\nname = Wide\n\n[legend]\nW = normal #FFFFFF\n\n[layout]\nWW  \nWW";
+    let mut wide = recorded_session();
+    wide.course = Course::Custom(level.to_owned());
+    let parsed = Replay::parse(&wide.to_text()).unwrap();
+    assert_eq!(parsed.course, wide.course);
+    let positions =
+        |replay: &Replay| -> Vec<Vec2> { replay.world().bricks().iter().map(|b| b.pos).collect() };
+    assert_eq!(positions(&parsed), positions(&wide));
+
+    let mut endless = recorded_session();
+    endless.course = Course::Endless;
+    let parsed = Replay::parse(&endless.to_text()).unwrap();
+    assert_eq!(parsed.course, Course::Endless);
+    assert_eq!(parsed.run().state_hash(), endless.run().state_hash());
+}
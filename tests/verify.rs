@@ -0,0 +1,36 @@
+//! Plays the replay corpus in `tests/replays` and checks each one still ends
+//! the way it was recorded. If gameplay changed on purpose, rewrite the
+//! expectations with `cargo run --bin rayball-verify -- --bless tests/replays`.
+
+use std::path::Path;
+
+use rayball::replay::Replay;
+
+#[test]
+fn replay_corpus_still_plays_the_same() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/replays");
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "replay"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no replays in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let replay = Replay::load(file).unwrap_or_else(|e| panic!("{}: {e}", file.display()));
+        match replay.verify() {
+            Ok(mismatches) if mismatches.is_empty() => {}
+            Ok(mismatches) => {
+                failures.push(format!("{}: {}", file.display(), mismatches.join("; ")))
+            }
+            Err(e) => failures.push(format!("{}: {e}", file.display())),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "replays diverged:\n{}",
+        failures.join("\n")
+    );
+}
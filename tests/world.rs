@@ -0,0 +1,331 @@
+use macroquad::prelude::*;
+use rayball::components::{Brick, BrickKind, Status};
+use rayball::constants::*;
+use rayball::input::TickInput;
+use rayball::levels::Course;
+use rayball::systems::descent::{ROW_POINTS, START_ROWS};
+use rayball::world::{GameEvent, World};
+
+const ARENA: Vec2 = vec2(800., 600.);
+/// Ticks in one second of play.
+const SECOND: u32 = TICK_RATE as u32;
+
+fn launch() -> TickInput {
+    TickInput {
+        launch: true,
+        ..Default::default()
+    }
+}
+
+fn hold_left() -> TickInput {
+    TickInput {
+        left: true,
+        ..Default::default()
+    }
+}
+
+fn hold_right() -> TickInput {
+    TickInput {
+        right: true,
+        ..Default::default()
+    }
+}
+
+/// Runs `ticks` ticks of the same input and returns every event raised.
+fn run(world: &mut World, input: TickInput, ticks: u32) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    for _ in 0..ticks {
+        world.tick(input);
+        events.extend(world.drain_events());
+    }
+    events
+}
+
+#[test]
+fn ball_waits_on_the_paddle_until_launched() {
+    let mut world = World::new(ARENA, 1);
+    run(&mut world, TickInput::default(), SECOND);
+    assert!(world.balls[0].status == Status::Start);
+
+    world.tick(launch());
+    let start = world.balls[0].pos;
+    run(&mut world, TickInput::default(), 10);
+    assert!(world.balls[0].status == Status::Running);
+    assert!(world.balls[0].pos.y < start.y);
+}
+
+#[test]
+fn dodging_every_ball_ends_the_game() {
+    let mut world = World::new(ARENA, 2);
+    let mut events = Vec::new();
+    for i in 0..MAX_LIVES {
+        // Wait for the ball to reach the paddle, launch, then step aside to
+        // whichever side the ball isn't on
+        events.extend(run(&mut world, TickInput::default(), SECOND));
+        world.tick(launch());
+        let dodge = if i % 2 == 0 {
+            hold_left()
+        } else {
+            hold_right()
+        };
+        events.extend(run(&mut world, dodge, 10 * SECOND));
+    }
+
+    assert_eq!(world.lives, 0);
+    assert!(world.ball_status() == Status::Dead);
+    let drops = events
+        .iter()
+        .filter(|e| matches!(e, GameEvent::BallDropped(_)))
+        .count();
+    assert_eq!(drops, MAX_LIVES as usize);
+    assert!(matches!(events.last(), Some(GameEvent::GameOver)));
+}
+
+#[test]
+fn clearing_the_bricks_moves_to_the_next_stage() {
+    let mut world = World::new(ARENA, 3);
+    let mut bricks = world.bricks().to_vec();
+    bricks.iter_mut().for_each(|b| b.die());
+    world.set_bricks(bricks);
+
+    let events = run(&mut world, TickInput::default(), 1);
+    assert_eq!(world.stage, 1);
+    assert!(world.bricks().iter().any(|b| b.active));
+    assert!(matches!(events[..], [GameEvent::StageStarted(1)]));
+}
+
+#[test]
+fn nothing_moves_on_once_the_game_is_over() {
+    for course in [Course::default(), Course::Endless] {
+        let mut world = World::with_course(ARENA, 3, course);
+        world.lives = 0;
+        let mut bricks = world.bricks().to_vec();
+        bricks.iter_mut().for_each(|b| b.die());
+        world.set_bricks(bricks);
+
+        let events = run(&mut world, TickInput::default(), SECOND);
+        assert_eq!(world.stage, 0);
+        assert!(!world.bricks().iter().any(|b| b.active));
+        assert!(!events.iter().any(|e| matches!(
+            e,
+            GameEvent::StageStarted(_) | GameEvent::RowAdded | GameEvent::GameWon
+        )));
+    }
+}
+
+#[test]
+fn same_seed_and_input_give_the_same_game() {
+    let play = || {
+        let mut world = World::new(ARENA, 42);
+        world.tick(launch());
+        for i in 0..20 * SECOND {
+            let input = if (i / 100) % 2 == 0 {
+                hold_right()
+            } else {
+                hold_left()
+            };
+            world.tick(input);
+        }
+        world
+    };
+
+    let (a, b) = (play(), play());
+    assert_eq!(a.score, b.score);
+    assert_eq!(a.lives, b.lives);
+    assert_eq!(a.balls[0].pos, b.balls[0].pos);
+    assert_eq!(a.particles.len(), b.particles.len());
+}
+
+#[test]
+fn pointer_centers_the_paddle_within_the_walls() {
+    let mut world = World::new(ARENA, 4);
+    let point = |x| TickInput {
+        pointer: Some(x),
+        ..Default::default()
+    };
+
+    world.tick(point(300.));
+    let platform = &world.platform;
+    assert_eq!(platform.pos.x + platform.width / 2., 300.);
+
+    world.tick(point(-50.));
+    assert_eq!(world.platform.pos.x, 0.);
+    world.tick(point(ARENA.x + 50.));
+    assert_eq!(world.platform.pos.x + world.platform.width, ARENA.x);
+}
+
+#[test]
+fn a_moving_paddle_puts_english_on_the_ball() {
+    let bounce = |input: TickInput| {
+        let mut world = World::new(ARENA, 5);
+        world.tick(launch());
+        let platform = &world.platform;
+        let ball = &mut world.balls[0];
+        ball.pos = vec2(
+            platform.pos.x + platform.width / 2.,
+            platform.pos.y - ball.radius - 10.,
+        );
+        ball.velocity = vec2(0., VELOCITY);
+        let events = run(&mut world, input, 10);
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, GameEvent::BallHitPlatform(_)))
+        );
+        (world.balls[0].velocity.x, world.balls[0].spin)
+    };
+
+    let (still, still_spin) = bounce(TickInput::default());
+    assert_eq!(still_spin, 0.);
+    let (right, right_spin) = bounce(hold_right());
+    assert!(right > still, "{right} <= {still}");
+    assert!(right_spin > 0.);
+    let (left, left_spin) = bounce(hold_left());
+    assert!(left < still, "{left} >= {still}");
+    assert!(left_spin < 0.);
+}
+
+#[test]
+fn the_sticky_paddle_holds_balls_where_they_land() {
+    let mut world = World::new(ARENA, 5);
+    world.tick(launch());
+    world.platform.sticky = true;
+    let second = world.balls[0].clone();
+    world.balls.push(second);
+    let (left, width, top) = (
+        world.platform.pos.x,
+        world.platform.width,
+        world.platform.pos.y,
+    );
+    for (ball, x) in world.balls.iter_mut().zip([left + 15., left + width - 15.]) {
+        ball.pos = vec2(x, top - ball.radius - 10.);
+        ball.velocity = vec2(0., VELOCITY);
+    }
+
+    run(&mut world, TickInput::default(), 10);
+    assert!(world.balls.iter().all(|b| b.status == Status::Start));
+    // Carried along by the paddle without sliding to its center
+    run(&mut world, hold_right(), 20);
+    let moved_to = world.platform.pos.x;
+    assert!(moved_to > left);
+    assert!((world.balls[0].pos.x - (moved_to + 15.)).abs() < 0.01);
+    assert!((world.balls[1].pos.x - (moved_to + width - 15.)).abs() < 0.01);
+}
+
+#[test]
+fn the_ball_speeds_up_to_the_stage_limit() {
+    let mut world = World::new(ARENA, 2);
+    let start = world.speed;
+    assert_eq!(start, world.pace.speed);
+
+    // Waiting on the paddle doesn't count
+    run(&mut world, TickInput::default(), SECOND);
+    assert_eq!(world.speed, start);
+
+    run(&mut world, launch(), SECOND);
+    assert!(world.speed > start);
+    let flying = world.balls[0].velocity.length();
+    assert!(
+        (flying - world.speed).abs() < 0.01,
+        "{flying} != {}",
+        world.speed
+    );
+
+    world.speed = world.pace.max_speed - 0.01;
+    run(&mut world, TickInput::default(), 10);
+    assert_eq!(world.speed, world.pace.max_speed);
+}
+
+#[test]
+fn a_flat_ball_is_tipped_upwards() {
+    let mut world = World::new(ARENA, 3);
+    world.tick(launch());
+    let ball = &mut world.balls[0];
+    ball.pos = ARENA / 2. + vec2(0., 100.);
+    ball.velocity = vec2(-VELOCITY, 0.);
+    world.tick(TickInput::default());
+
+    let dir = world.balls[0].velocity.normalize();
+    assert!(dir.x < 0.);
+    assert!((dir.y + world.pace.min_rise()).abs() < 1e-4, "{dir}");
+}
+
+#[test]
+fn the_seam_between_two_bricks_bounces_like_a_wall() {
+    let mut world = World::new(ARENA, 4);
+    world.tick(launch());
+    let color = Color::from_hex(0xffffff);
+    let left = Brick::new(vec2(300., 200.), BrickKind::Normal, 1, color);
+    let right = Brick::new(
+        vec2(300. + left.width + 15., 200.),
+        BrickKind::Normal,
+        1,
+        color,
+    );
+    let seam = left.pos.x + left.width + 7.5;
+    // Keeps the stage going once the pair is gone
+    let spare = Brick::new(vec2(20., 20.), BrickKind::Normal, 1, color);
+    world.set_bricks(vec![left, right, spare]);
+
+    let ball = &mut world.balls[0];
+    ball.pos = vec2(seam, 300.);
+    ball.velocity = vec2(0., -VELOCITY);
+    let events = run(&mut world, TickInput::default(), SECOND / 4);
+
+    let count = |f: fn(&GameEvent) -> bool| events.iter().filter(|e| f(e)).count();
+    assert_eq!(count(|e| matches!(e, GameEvent::BrickCollision(..))), 2);
+    assert_eq!(count(|e| matches!(e, GameEvent::BrickDestroyed(..))), 2);
+    let velocity = world.balls[0].velocity;
+    assert!(velocity.y > 0.);
+    assert!(velocity.x.abs() < 1e-3, "{velocity}");
+}
+
+#[test]
+fn endless_rows_push_the_field_down_until_it_reaches_the_paddle() {
+    let mut world = World::with_course(ARENA, 9, Course::Endless);
+    let lowest = world.bricks().iter().map(|b| b.row).max();
+    assert_eq!(lowest, Some(START_ROWS - 1));
+    world.tick(launch());
+
+    let mut events = Vec::new();
+    for _ in 0..SECOND {
+        // Keep the ball in play and rush the rows in, one a tick
+        world.descent.as_mut().unwrap().timer = 0.;
+        let x = world.balls[0].pos.x;
+        world.tick(TickInput {
+            pointer: Some(x),
+            ..Default::default()
+        });
+        events.extend(world.drain_events());
+        if events.iter().any(|e| matches!(e, GameEvent::GameOver)) {
+            break;
+        }
+    }
+
+    let rows = world.descent.as_ref().unwrap().rows;
+    let added = events
+        .iter()
+        .filter(|e| matches!(e, GameEvent::RowAdded))
+        .count();
+    assert!(events.iter().any(|e| matches!(e, GameEvent::GameOver)));
+    assert_eq!(added, rows as usize);
+    assert_eq!(world.lives, 0);
+    assert!(world.balls.iter().all(|b| b.status == Status::Dead));
+    // The row that reached the paddle doesn't count
+    assert_eq!(world.score, (rows - 1) * ROW_POINTS);
+    let paddle = world.platform.pos.y;
+    assert!(
+        world
+            .bricks()
+            .iter()
+            .any(|b| b.active && b.pos.y + b.height >= paddle)
+    );
+}
+
+#[test]
+fn endless_rows_wait_for_the_ball() {
+    let mut world = World::with_course(ARENA, 9, Course::Endless);
+    let events = run(&mut world, TickInput::default(), 60 * SECOND);
+    assert!(!events.iter().any(|e| matches!(e, GameEvent::RowAdded)));
+    assert_eq!(world.descent.as_ref().unwrap().rows, 0);
+}